@@ -0,0 +1,235 @@
+use std::rc::Rc;
+
+use remotro::balatro::{
+    blinds::BossBlind, consumables::Planet, deck::PlayingCard, jokers::Joker, play::Play,
+};
+
+use crate::{
+    big_num::BigNum,
+    decks::DeckRules,
+    hand_levels::{HandLevels, planet_hand},
+    play::{GameState, is_supported, score},
+};
+
+/// How many recent hands offers and blinds are judged against.
+const BENCHMARK_HANDS: usize = 8;
+/// What doubling the run's scores is worth in dollars, to weigh score against
+/// money.
+const DOUBLING_WORTH: f64 = 20.0;
+/// How many rounds ahead money spent now would have earned interest over.
+pub const ROUNDS_AHEAD: f64 = 3.0;
+
+/// Hands played recently, kept so shop offers and boss blinds can be scored
+/// without a hand in front of us.
+#[derive(Clone, Debug, Default)]
+pub struct Benchmark {
+    /// The cards played and the cards held for each hand.
+    hands: Vec<(Vec<PlayingCard>, Vec<PlayingCard>)>,
+    levels: HandLevels,
+    /// The jokers owned when the last hand was played.
+    jokers: Vec<Joker>,
+    rules: Option<DeckRules>,
+    hands_per_round: u32,
+    discards: u32,
+    /// How many cards the deck held when the last hand was played.
+    deck_size: usize,
+}
+
+impl Benchmark {
+    /// Takes note of the cards at `cards` about to be played from `play`.
+    pub fn record(&mut self, play: &Play, cards: &[usize]) {
+        let (played, held) = play
+            .hand()
+            .iter()
+            .enumerate()
+            .filter_map(|(i, c)| Some((i, c.card.clone()?)))
+            .partition::<Vec<_>, _>(|(i, _)| cards.contains(i));
+        self.hands.push((
+            played.into_iter().map(|(_, c)| c).collect(),
+            held.into_iter().map(|(_, c)| c).collect(),
+        ));
+        if self.hands.len() > BENCHMARK_HANDS {
+            self.hands.remove(0);
+        }
+        self.levels = HandLevels::from_play(play);
+        self.jokers = play.jokers().to_vec();
+        self.rules = Some(DeckRules::for_deck(play.run_info().deck));
+        self.hands_per_round = self.hands_per_round.max(play.hands().into());
+        self.discards = self.discards.max(play.discards().into());
+        self.deck_size = play.deck_size();
+    }
+
+    pub fn rules(&self) -> Option<DeckRules> {
+        self.rules
+    }
+
+    /// How much `boss` scales the benchmark hands' scores, from debuffing
+    /// their cards or halving their base. 1 when there's nothing to go on.
+    pub fn boss_factor(&self, boss: BossBlind) -> f64 {
+        let jokers: Vec<&Joker> = self.jokers.iter().collect();
+        let (Some(with), Some(without)) = (
+            self.score(&jokers, &self.levels, Some(boss)),
+            self.score(&jokers, &self.levels, None),
+        ) else {
+            return 1.0;
+        };
+        let ratio = with.0.to_f64() / without.0.to_f64();
+        if ratio.is_finite() { ratio } else { 1.0 }
+    }
+
+    /// Records a planet card being used, which the play screen won't show
+    /// until the next hand.
+    pub fn use_planet(&mut self, planet: Planet) {
+        self.levels.level_up(planet_hand(planet), 1);
+    }
+
+    /// The total expected score and money of the benchmark hands with
+    /// `jokers` owned, hands at `levels` and played against `boss`. Jokers the
+    /// engine can't score are left out, so they count as worth nothing rather
+    /// than making every offer worth nothing. `None` if the hands can't be
+    /// scored.
+    fn score(
+        &self,
+        jokers: &[&Joker],
+        levels: &HandLevels,
+        boss: Option<BossBlind>,
+    ) -> Option<(BigNum, f64)> {
+        let rules = self.rules?;
+        let levels = Rc::new(levels.clone());
+        let jokers: Vec<&Joker> = jokers
+            .iter()
+            .copied()
+            .filter(|j| is_supported(&j.kind))
+            .collect();
+        let mut total = (BigNum::ZERO, 0.0);
+        for (played, held) in &self.hands {
+            let state = GameState {
+                selected: Vec::new(),
+                held: Vec::new(),
+                debuffed: Vec::new(),
+                jokers: jokers.clone(),
+                hand_levels: Rc::clone(&levels),
+                money: 0,
+                hands: self.hands_per_round,
+                discards: self.discards,
+                rules,
+                boss,
+            }
+            .with_cards(played.iter().collect(), held.iter().collect());
+            let scores = score(&state).ok()?;
+            total.0 += scores.expected();
+            total.1 += scores.expected_money();
+        }
+        Some(total)
+    }
+
+    /// What the benchmark hands going from scoring `before` to scoring
+    /// `after` is worth in dollars over the next few rounds.
+    fn worth(&self, before: Option<(BigNum, f64)>, after: Option<(BigNum, f64)>) -> f64 {
+        let (Some(before), Some(after)) = (before, after) else {
+            return 0.0;
+        };
+        let ratio = after.0.to_f64() / before.0.to_f64();
+        let score_worth = if ratio.is_finite() && ratio > 0.0 {
+            ratio.log2() * DOUBLING_WORTH
+        } else {
+            0.0
+        };
+        let hands = self.hands.len().max(1) as f64;
+        let money_worth =
+            (after.1 - before.1) / hands * f64::from(self.hands_per_round) * ROUNDS_AHEAD;
+        score_worth + money_worth
+    }
+
+    /// What one more hand every round is worth in dollars, since it scores
+    /// that much more of the blind.
+    pub fn extra_hand_worth(&self) -> f64 {
+        if self.hands_per_round == 0 {
+            return 0.0;
+        }
+        let hands = f64::from(self.hands_per_round);
+        ((hands + 1.0) / hands).log2() * DOUBLING_WORTH
+    }
+
+    /// What one more discard every round is worth in dollars. Only jokers
+    /// that count the discards left see it, since what a discard digs up
+    /// depends on hands the benchmark doesn't have.
+    pub fn extra_discard_worth(&self, owned: &[Joker]) -> f64 {
+        let jokers: Vec<&Joker> = owned.iter().collect();
+        let mut more = self.clone();
+        more.discards += 1;
+        self.worth(
+            self.score(&jokers, &self.levels, None),
+            more.score(&jokers, &self.levels, None),
+        )
+    }
+
+    pub fn joker_worth(&self, owned: &[Joker], joker: &Joker) -> f64 {
+        let jokers: Vec<&Joker> = owned.iter().collect();
+        let mut with = jokers.clone();
+        with.push(joker);
+        self.worth(
+            self.score(&jokers, &self.levels, None),
+            self.score(&with, &self.levels, None),
+        )
+    }
+
+    pub fn planet_worth(&self, owned: &[Joker], planet: Planet) -> f64 {
+        let mut levels = self.levels.clone();
+        levels.level_up(planet_hand(planet), 1);
+        self.levels_worth(owned, &levels)
+    }
+
+    /// Black Hole levels up every hand at once.
+    pub fn black_hole_worth(&self, owned: &[Joker]) -> f64 {
+        let mut levels = self.levels.clone();
+        for kind in self.levels.iter().map(|h| h.kind) {
+            levels.level_up(kind, 1);
+        }
+        self.levels_worth(owned, &levels)
+    }
+
+    fn levels_worth(&self, owned: &[Joker], levels: &HandLevels) -> f64 {
+        let jokers: Vec<&Joker> = owned.iter().collect();
+        self.worth(
+            self.score(&jokers, &self.levels, None),
+            self.score(&jokers, levels, None),
+        )
+    }
+
+    /// What using `change` on `cards` plain cards of the deck is worth in
+    /// dollars. The first plain card played in each benchmark hand gets
+    /// changed, and what that's worth is scaled down to how often a hand
+    /// holds one of the changed cards.
+    pub fn card_change_worth(
+        &self,
+        owned: &[Joker],
+        cards: usize,
+        change: fn(&mut PlayingCard),
+    ) -> f64 {
+        let Some(rules) = self.rules else {
+            return 0.0;
+        };
+        if self.deck_size == 0 {
+            return 0.0;
+        }
+        let jokers: Vec<&Joker> = owned.iter().collect();
+        let mut changed = self.clone();
+        for (played, _) in &mut changed.hands {
+            if let Some(card) = played.iter_mut().find(|c| is_plain(c)) {
+                change(card);
+            }
+        }
+        let held = (cards * rules.hand_size) as f64 / self.deck_size as f64;
+        self.worth(
+            self.score(&jokers, &self.levels, None),
+            changed.score(&jokers, &self.levels, None),
+        ) * held.min(1.0)
+    }
+}
+
+/// Whether `card` has no enhancement or seal yet. Consumables that change
+/// cards are only aimed at these, so nothing already on a card is lost.
+pub fn is_plain(card: &PlayingCard) -> bool {
+    card.enhancement.is_none() && card.seal.is_none()
+}
@@ -0,0 +1,279 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    iter::Sum,
+    ops::{Add, AddAssign, Mul, MulAssign},
+};
+
+/// A non-negative number stored as a mantissa and a power of ten, so scores
+/// can keep growing long after an `f64` would have hit infinity.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BigNum {
+    /// Either 0 or in `1.0..10.0`.
+    mantissa: f64,
+    exponent: i64,
+}
+
+/// Below this Balatro writes numbers out in full, above it in scientific
+/// notation.
+const E_SWITCH_POINT: f64 = 1e11;
+
+impl BigNum {
+    pub const ZERO: Self = Self {
+        mantissa: 0.0,
+        exponent: 0,
+    };
+
+    fn new(mantissa: f64, exponent: i64) -> Self {
+        if mantissa == 0.0 || !mantissa.is_finite() {
+            return Self {
+                mantissa,
+                exponent: 0,
+            };
+        }
+        let shift = mantissa.abs().log10().floor();
+        let (mut mantissa, mut exponent) = (mantissa / 10f64.powf(shift), exponent + shift as i64);
+        // log10 rounds, so just under a power of ten the shift can be one off
+        if mantissa.abs() >= 10.0 {
+            mantissa /= 10.0;
+            exponent += 1;
+        } else if mantissa.abs() < 1.0 {
+            mantissa *= 10.0;
+            exponent -= 1;
+        }
+        Self { mantissa, exponent }
+    }
+
+    /// The closest `f64`, which is infinite once the number is too big.
+    pub fn to_f64(self) -> f64 {
+        self.mantissa * 10f64.powf(self.exponent as f64)
+    }
+
+    pub fn floor(self) -> Self {
+        if self.exponent > 17 {
+            self
+        } else {
+            Self::from(self.to_f64().floor())
+        }
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        if other > self { other } else { self }
+    }
+
+    pub fn midpoint(self, other: Self) -> Self {
+        (self + other) * 0.5
+    }
+}
+
+impl From<f64> for BigNum {
+    fn from(value: f64) -> Self {
+        Self::new(value, 0)
+    }
+}
+
+impl Add for BigNum {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let (big, small) = if self >= other {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        if small.mantissa == 0.0 {
+            return big;
+        }
+        let gap = big.exponent - small.exponent;
+        // Past 17 digits the smaller number can't change the bigger one
+        if gap > 17 {
+            return big;
+        }
+        Self::new(
+            big.mantissa + small.mantissa / 10f64.powi(gap as i32),
+            big.exponent,
+        )
+    }
+}
+
+impl Add<f64> for BigNum {
+    type Output = Self;
+
+    fn add(self, other: f64) -> Self {
+        self + Self::from(other)
+    }
+}
+
+impl AddAssign for BigNum {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl AddAssign<f64> for BigNum {
+    fn add_assign(&mut self, other: f64) {
+        *self = *self + other;
+    }
+}
+
+impl Mul for BigNum {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.mantissa * other.mantissa,
+            self.exponent + other.exponent,
+        )
+    }
+}
+
+impl Mul<f64> for BigNum {
+    type Output = Self;
+
+    fn mul(self, other: f64) -> Self {
+        self * Self::from(other)
+    }
+}
+
+impl MulAssign for BigNum {
+    fn mul_assign(&mut self, other: Self) {
+        *self = *self * other;
+    }
+}
+
+impl MulAssign<f64> for BigNum {
+    fn mul_assign(&mut self, other: f64) {
+        *self = *self * other;
+    }
+}
+
+impl Sum for BigNum {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+impl PartialEq for BigNum {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for BigNum {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self.mantissa == 0.0, other.mantissa == 0.0) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => match self.exponent.cmp(&other.exponent) {
+                Ordering::Equal => self.mantissa.partial_cmp(&other.mantissa),
+                ordering => Some(ordering),
+            },
+        }
+    }
+}
+
+/// Formats the number the way Balatro displays scores: in full with commas
+/// up to 100 billion, then as e.g. `1.234e11`, and `naneinf` once broken.
+impl fmt::Display for BigNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.mantissa.is_finite() {
+            return write!(f, "naneinf");
+        }
+        let value = self.to_f64();
+        if value < E_SWITCH_POINT {
+            if value != value.floor() && value < 100.0 {
+                return if value >= 10.0 {
+                    write!(f, "{value:.1}")
+                } else {
+                    write!(f, "{value:.2}")
+                };
+            }
+            let digits = format!("{value:.0}");
+            let mut formatted = String::new();
+            for (i, digit) in digits.chars().enumerate() {
+                if i > 0 && (digits.len() - i) % 3 == 0 {
+                    formatted.push(',');
+                }
+                formatted.push(digit);
+            }
+            return write!(f, "{formatted}");
+        }
+        let (mut mantissa, mut exponent) = (self.mantissa, self.exponent);
+        // Round to 3 places first so 9.9996 shows as 1.000 of the next power
+        if (mantissa * 1000.0).round() >= 10_000.0 {
+            mantissa /= 10.0;
+            exponent += 1;
+        }
+        match exponent {
+            100.. => write!(f, "{mantissa:.1}e{exponent}"),
+            10.. => write!(f, "{mantissa:.2}e{exponent}"),
+            _ => write!(f, "{mantissa:.3}e{exponent}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(mantissa: f64, exponent: i64) -> BigNum {
+        BigNum { mantissa, exponent }
+    }
+
+    /// The largest `f64` below `value`.
+    fn just_below(value: f64) -> f64 {
+        f64::from_bits(value.to_bits() - 1)
+    }
+
+    #[test]
+    fn mantissa_stays_normalised_just_under_a_power_of_ten() {
+        for value in [just_below(1000.0), just_below(1e15), 1000.0, 1e15] {
+            let n = BigNum::from(value);
+            assert!((1.0..10.0).contains(&n.mantissa), "{value} -> {n:?}");
+        }
+        assert_eq!(BigNum::from(just_below(1000.0)).exponent, 2);
+        for k in 0..=22 {
+            let n = BigNum::from(10f64.powi(k));
+            assert_eq!((n.mantissa, n.exponent), (1.0, i64::from(k)));
+        }
+    }
+
+    #[test]
+    fn adds_across_exponents() {
+        assert_eq!(BigNum::from(9_500.0) + BigNum::from(500.0), big(1.0, 4));
+        assert_eq!(big(2.0, 400) + big(5.0, 399), big(2.5, 400));
+        // Too small to change the bigger number at all
+        assert_eq!(big(1.0, 40) + BigNum::from(5.0), big(1.0, 40));
+        assert_eq!(BigNum::ZERO + big(3.0, 500), big(3.0, 500));
+    }
+
+    #[test]
+    fn multiplies_across_exponents() {
+        assert_eq!(big(5.0, 200) * big(4.0, 200), big(2.0, 401));
+        assert_eq!(big(2.5, 3) * 4.0, big(1.0, 4));
+        assert_eq!(big(5.0, 300) * BigNum::ZERO, BigNum::ZERO);
+    }
+
+    #[test]
+    fn orders_across_powers_of_ten() {
+        for k in 1..=30 {
+            let power = big(1.0, k);
+            assert!(big(9.999, k - 1) < power);
+            assert!(big(1.001, k) > power);
+            assert!(BigNum::ZERO < power);
+        }
+        assert!(BigNum::from(just_below(1000.0)) < BigNum::from(1000.0));
+    }
+
+    #[test]
+    fn displays_like_balatro() {
+        assert_eq!(BigNum::from(99_999_999_999.0).to_string(), "99,999,999,999");
+        assert_eq!(BigNum::from(1e11).to_string(), "1.000e11");
+        assert_eq!(big(9.9996, 11).to_string(), "1.000e12");
+        assert_eq!(big(1.234, 99).to_string(), "1.23e99");
+        assert_eq!(big(1.0, 100).to_string(), "1.0e100");
+        assert_eq!(BigNum::from(f64::INFINITY).to_string(), "naneinf");
+        assert_eq!(BigNum::from(f64::NAN).to_string(), "naneinf");
+    }
+}
@@ -0,0 +1,98 @@
+use remotro::balatro::{
+    blinds::{
+        BossBlind, CurrentBlind, SelectBlind,
+        Tag::{self, *},
+    },
+    play::Play,
+};
+
+use crate::{benchmark::Benchmark, big_num::BigNum, decks::DeckRules, play::ScoreDistribution};
+
+/// How many times over a blind's chips the run should be scoring before it
+/// risks skipping the blind before it.
+const SAFETY_MARGIN: f64 = 2.0;
+/// Below this much money, the blind's reward is worth more than most tags.
+const MIN_MONEY: i64 = 10;
+
+/// How well the run is doing, remembered from the play screen so the blind
+/// select screen can be judged.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Form {
+    /// The expected score of the best hand played last round.
+    best_hand: BigNum,
+    round_best: BigNum,
+    hands: u32,
+    money: i64,
+    rules: Option<DeckRules>,
+}
+
+impl Form {
+    /// Takes note of a hand about to be played.
+    pub fn record(&mut self, play: &Play, scores: &ScoreDistribution) {
+        self.round_best = self.round_best.max(scores.expected());
+        self.hands = self.hands.max(play.hands().into());
+        self.money = play.money().into();
+        self.rules = Some(DeckRules::for_deck(play.run_info().deck));
+    }
+
+    pub fn new_round(&mut self) {
+        if self.round_best > BigNum::ZERO {
+            self.best_hand = self.round_best;
+        }
+        self.round_best = BigNum::ZERO;
+    }
+
+    /// Whether the run looks strong enough to beat a blind needing `chips`
+    /// in `hands` hands, when the blind scales its scores by `factor`.
+    fn can_beat(&self, chips: f64, hands: u32, factor: f64) -> bool {
+        self.best_hand * (f64::from(hands) * factor) >= BigNum::from(chips * SAFETY_MARGIN)
+    }
+}
+
+/// The tag to skip the current blind for, or `None` to play it. Only skips
+/// for tags worth more than the blind's reward, and only when the run looks
+/// able to beat the blinds still to come this ante, with the boss blind's
+/// effect on the `benchmark` hands taken into account.
+pub fn tag_to_skip_for(blinds: &SelectBlind, form: &Form, benchmark: &Benchmark) -> Option<Tag> {
+    let boss = blinds.boss().boss;
+    // The Needle only allows a single hand
+    let boss_hands = if boss == Some(BossBlind::TheNeedle) {
+        1
+    } else {
+        form.hands
+    };
+    let boss_blind = (
+        blinds.boss().chips,
+        boss_hands,
+        boss.map_or(1.0, |b| benchmark.boss_factor(b)),
+    );
+    let (tag, next_tag, upcoming) = match blinds.current() {
+        CurrentBlind::Small => (
+            blinds.small().tag?,
+            blinds.big().tag,
+            vec![(blinds.big().chips, form.hands, 1.0), boss_blind],
+        ),
+        CurrentBlind::Big => (blinds.big().tag?, None, vec![boss_blind]),
+        CurrentBlind::Boss => return None,
+    };
+    let valuable = match tag {
+        // Double Tag copies the next tag taken, so it's only as good as that
+        Double => next_tag.is_some_and(is_valuable),
+        Investment => true,
+        tag => is_valuable(tag) && form.money >= MIN_MONEY,
+    };
+    // Anaglyph Deck gives a Double Tag after each boss, doubling the first
+    // tag of the ante. It hasn't had one before any hands are played
+    let doubled = blinds.current() == CurrentBlind::Small
+        && form.best_hand > BigNum::ZERO
+        && form.rules.is_some_and(|r| r.double_tag_after_boss);
+    let worth_it = valuable || doubled && is_valuable(tag);
+    let can_beat_all = upcoming
+        .iter()
+        .all(|&(chips, hands, factor)| form.can_beat(chips as f64, hands, factor));
+    (worth_it && can_beat_all).then_some(tag)
+}
+
+fn is_valuable(tag: Tag) -> bool {
+    matches!(tag, Negative | Polychrome | Investment | Double | Voucher)
+}
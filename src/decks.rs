@@ -0,0 +1,115 @@
+use remotro::balatro::{
+    deck::{
+        Rank::{self, *},
+        Suit::{self, *},
+    },
+    menu::Deck,
+};
+
+const ALL_SUITS: &[Suit] = &[Spades, Hearts, Clubs, Diamonds];
+const ALL_RANKS: &[Rank] = &[
+    Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace,
+];
+const NO_FACES: &[Rank] = &[Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Ace];
+
+/// How the deck a run was started with changes scoring and strategy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeckRules {
+    /// Plasma Deck: chips and mult are averaged before they're multiplied.
+    pub balance_chips_and_mult: bool,
+    /// The suits the deck starts with. Checkered only has Spades and Hearts.
+    pub suits: &'static [Suit],
+    /// The ranks the deck starts with. Abandoned has no face cards.
+    pub ranks: &'static [Rank],
+    /// Erratic Deck starts with random cards, so `suits` and `ranks` say
+    /// nothing about what's in it.
+    pub random_cards: bool,
+    pub hand_size: usize,
+    pub joker_slots: usize,
+    pub consumable_slots: usize,
+    /// Anaglyph Deck: a Double Tag after every boss, which doubles the next
+    /// tag taken for skipping a blind.
+    pub double_tag_after_boss: bool,
+    /// Zodiac and Magic Decks start with vouchers that make consumables worth
+    /// more, so they're worth buying more often.
+    pub favours_consumables: bool,
+}
+
+const STANDARD: DeckRules = DeckRules {
+    balance_chips_and_mult: false,
+    suits: ALL_SUITS,
+    ranks: ALL_RANKS,
+    random_cards: false,
+    hand_size: 8,
+    joker_slots: 5,
+    consumable_slots: 2,
+    double_tag_after_boss: false,
+    favours_consumables: false,
+};
+
+impl DeckRules {
+    pub fn for_deck(deck: Deck) -> Self {
+        match deck {
+            Deck::Plasma => Self {
+                balance_chips_and_mult: true,
+                ..STANDARD
+            },
+            Deck::Checkered => Self {
+                suits: &[Spades, Hearts],
+                ..STANDARD
+            },
+            Deck::Abandoned => Self {
+                ranks: NO_FACES,
+                ..STANDARD
+            },
+            Deck::Erratic => Self {
+                random_cards: true,
+                ..STANDARD
+            },
+            Deck::Anaglyph => Self {
+                double_tag_after_boss: true,
+                ..STANDARD
+            },
+            Deck::Zodiac => Self {
+                favours_consumables: true,
+                ..STANDARD
+            },
+            // Crystal Ball gives an extra consumable slot
+            Deck::Magic => Self {
+                consumable_slots: 3,
+                favours_consumables: true,
+                ..STANDARD
+            },
+            Deck::Painted => Self {
+                hand_size: 10,
+                joker_slots: 4,
+                ..STANDARD
+            },
+            Deck::Black => Self {
+                joker_slots: 6,
+                ..STANDARD
+            },
+            _ => STANDARD,
+        }
+    }
+
+    /// Every card the deck starts with, or `None` for Erratic Deck where
+    /// there's no telling.
+    pub fn starting_cards(&self) -> Option<Vec<(Rank, Suit)>> {
+        if self.random_cards {
+            return None;
+        }
+        Some(
+            self.suits
+                .iter()
+                .flat_map(|&suit| {
+                    // Checkered has two of each card in its suits
+                    let copies = ALL_SUITS.len() / self.suits.len();
+                    self.ranks
+                        .iter()
+                        .flat_map(move |&rank| std::iter::repeat_n((rank, suit), copies))
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,143 @@
+use std::cmp::Ordering;
+
+use remotro::balatro::{deck::PlayingCard, play::Play};
+
+use crate::{
+    big_num::BigNum,
+    draw_pile::DrawPile,
+    play::{GameState, ScoreError, score},
+    search::combinations,
+};
+
+/// The most cards Balatro lets you discard or play at once.
+const MAX_DISCARDED: usize = 5;
+/// The most cards that can be kept towards the hand being drawn for.
+const MAX_KEPT: usize = 5;
+/// How many possible draws every discard is first judged on, to pick out the
+/// ones worth a closer look.
+const COARSE_DRAWS: u64 = 2;
+/// How many discards get the closer look.
+const SHORTLIST: usize = 8;
+/// How many possible draws each shortlisted discard is judged on.
+const DRAWS: u64 = 16;
+
+/// The hand indices to get rid of, by discarding or playing them, for the
+/// best chance of then holding a hand that reaches `target`. Comes with that
+/// chance and the expected score of the hand. `base` is `play` snapshotted
+/// with nothing selected.
+pub fn best_draw(
+    play: &Play,
+    base: &GameState,
+    pile: &DrawPile,
+    target: BigNum,
+) -> Result<Option<(Vec<usize>, (f64, BigNum))>, ScoreError> {
+    let hand: Vec<(usize, &PlayingCard)> = play
+        .hand()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| c.card.as_ref().map(|card| (i, card)))
+        .collect();
+    let unseen = pile.remaining();
+    let positions: Vec<usize> = (0..hand.len()).collect();
+    let fewest_kept = hand.len().saturating_sub(MAX_DISCARDED);
+    // A couple of draws is enough to rule out most of the cards to keep, so
+    // only the most promising get judged on every draw. So few draws leave
+    // lots of ties, which go to the keep with the better outs.
+    let mut shortlist = Vec::new();
+    for kept in fewest_kept..hand.len().min(MAX_KEPT + 1) {
+        for keep in combinations(&positions, kept) {
+            let value = judge_keep(base, &hand, &keep, &unseen, target, COARSE_DRAWS)?;
+            shortlist.push(((value, outs(base, pile, &hand, &keep)), keep));
+        }
+    }
+    shortlist.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    shortlist.truncate(SHORTLIST);
+    let mut best: Option<(Vec<usize>, (f64, BigNum))> = None;
+    for (_, keep) in shortlist {
+        let value = judge_keep(base, &hand, &keep, &unseen, target, DRAWS)?;
+        if best.as_ref().is_none_or(|(_, b)| value > *b) {
+            let thrown = positions
+                .iter()
+                .filter(|p| !keep.contains(p))
+                .map(|&p| hand[p].0)
+                .collect();
+            best = Some((thrown, value));
+        }
+    }
+    Ok(best)
+}
+
+/// The chance of reaching `target` and expected score when keeping the hand
+/// cards at `keep` and discarding the rest, averaged over `draws` draws.
+fn judge_keep(
+    base: &GameState,
+    hand: &[(usize, &PlayingCard)],
+    keep: &[usize],
+    unseen: &[&PlayingCard],
+    target: BigNum,
+    draws: u64,
+) -> Result<(f64, BigNum), ScoreError> {
+    let kept: Vec<&PlayingCard> = keep.iter().map(|&p| hand[p].1).collect();
+    let drawn_count = cards_drawn(base, hand.len(), kept.len()).min(unseen.len());
+    let mut chance = 0.0;
+    let mut expected = BigNum::ZERO;
+    for draw in 1..=draws {
+        let drawn = draw_cards(unseen, drawn_count, draw);
+        let positions: Vec<usize> = (0..drawn.len()).collect();
+        let mut best = (0.0, BigNum::ZERO);
+        for extra in 0..=(MAX_KEPT - kept.len()).min(drawn.len()) {
+            for added in combinations(&positions, extra) {
+                let mut selected = kept.clone();
+                selected.extend(added.iter().map(|&i| drawn[i]));
+                let held = drawn
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !added.contains(i))
+                    .map(|(_, &c)| c)
+                    .collect();
+                let scores = score(&base.clone().with_cards(selected, held))?;
+                best = max_value(best, (scores.chance_to_reach(target), scores.expected()));
+            }
+        }
+        chance += best.0;
+        expected += best.1;
+    }
+    Ok((chance / draws as f64, expected * (1.0 / draws as f64)))
+}
+
+/// The chance of drawing at least one card that shares a rank or suit with
+/// the hand cards at `keep`, which is what most hands get built from.
+fn outs(base: &GameState, pile: &DrawPile, hand: &[(usize, &PlayingCard)], keep: &[usize]) -> f64 {
+    let kept: Vec<&PlayingCard> = keep.iter().map(|&p| hand[p].1).collect();
+    pile.chance_of(cards_drawn(base, hand.len(), kept.len()), |card| {
+        kept.iter()
+            .any(|k| k.rank == card.rank || k.suit == card.suit)
+    })
+}
+
+/// How many cards get drawn after keeping `kept` of the `held` cards. The
+/// hand refills to its full size, which Juggler and the like can push past
+/// what the deck starts with.
+fn cards_drawn(base: &GameState, held: usize, kept: usize) -> usize {
+    base.rules.hand_size.max(held) - kept
+}
+
+fn max_value(a: (f64, BigNum), b: (f64, BigNum)) -> (f64, BigNum) {
+    if b > a { b } else { a }
+}
+
+/// `count` cards picked from `cards` without replacement, the same ones for
+/// the same `seed`.
+fn draw_cards<'a>(cards: &[&'a PlayingCard], count: usize, seed: u64) -> Vec<&'a PlayingCard> {
+    let mut seed = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    let mut pool = cards.to_vec();
+    for i in 0..count {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let j = i + (seed % (pool.len() - i) as u64) as usize;
+        pool.swap(i, j);
+    }
+    pool.truncate(count);
+    pool
+}
@@ -0,0 +1,169 @@
+use remotro::balatro::{deck::PlayingCard, play::Play};
+
+use crate::decks::DeckRules;
+
+/// Keeps track of which cards are still left to draw this round, starting from
+/// the deck the run began with.
+#[derive(Clone, Debug, Default)]
+pub struct DrawPile {
+    /// Every card in the deck, as last seen.
+    deck: Vec<PlayingCard>,
+    /// Cards that have been in hand this round, including the current hand.
+    drawn: Vec<PlayingCard>,
+    /// The hand as last seen, less any cards since played or discarded.
+    hand: Vec<PlayingCard>,
+    discarded_this_round: bool,
+    /// Glass cards from the last hand played that may have shattered, until
+    /// the deck size says whether they did.
+    may_have_shattered: Vec<PlayingCard>,
+}
+
+impl DrawPile {
+    /// A full deck for `rules`. Erratic Decks start out empty and are learnt
+    /// as cards are drawn.
+    pub fn new(rules: DeckRules) -> Self {
+        let deck = rules
+            .starting_cards()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(rank, suit)| PlayingCard {
+                rank,
+                suit,
+                enhancement: None,
+                edition: None,
+                seal: None,
+            })
+            .collect();
+        Self {
+            deck,
+            ..Self::default()
+        }
+    }
+
+    /// Shuffles every card back in, as the game does at the end of a round.
+    pub fn new_round(&mut self) {
+        self.drawn.clear();
+        self.hand.clear();
+        self.discarded_this_round = false;
+    }
+
+    /// Records the cards newly drawn into `play`'s hand. Cards the deck isn't
+    /// known to have, such as ones added from packs, are added to it.
+    /// Glass cards from the last hand are destroyed if the deck got smaller.
+    pub fn observe(&mut self, play: &Play) {
+        let mut previous = std::mem::take(&mut self.hand);
+        for card in play.hand().iter().filter_map(|c| c.card.as_ref()) {
+            self.hand.push(card.clone());
+            if let Some(i) = previous.iter().position(|c| same_card(c, card)) {
+                previous.swap_remove(i);
+                continue;
+            }
+            self.drawn.push(card.clone());
+            let in_deck = self.deck.iter().filter(|c| same_card(c, card)).count();
+            let drawn = self.drawn.iter().filter(|c| same_card(c, card)).count();
+            if in_deck < drawn {
+                self.deck.push(card.clone());
+            } else if let Some(c) = self.deck.iter_mut().find(|c| same_card(c, card)) {
+                // Keep its enhancement, edition and seal up to date
+                *c = card.clone();
+            }
+        }
+        // Shattering doesn't show on the play screen, but the deck shrinks.
+        // When only some of the Glass cards broke there's no telling which,
+        // so the first ones are taken
+        let shattered = self.deck.len().saturating_sub(play.deck_size());
+        for card in std::mem::take(&mut self.may_have_shattered)
+            .iter()
+            .take(shattered)
+        {
+            self.destroy(card);
+        }
+    }
+
+    /// Records the hand cards at `indices` being played. `may_shatter` has a
+    /// bit for each played card that could shatter as Glass, in the order
+    /// they sit in the hand.
+    pub fn play(&mut self, play: &Play, indices: &[usize], may_shatter: u8) {
+        self.may_have_shattered = play
+            .hand()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| indices.contains(i))
+            .filter_map(|(_, c)| c.card.as_ref())
+            .enumerate()
+            .filter(|(i, _)| may_shatter & (1 << i) != 0)
+            .map(|(_, card)| card.clone())
+            .collect();
+        self.leave_hand(play, indices);
+    }
+
+    /// Records the hand cards at `indices` being discarded. With Trading Card
+    /// a single card discarded first in the round is destroyed.
+    pub fn discard(&mut self, play: &Play, indices: &[usize], trading_card: bool) {
+        let left = self.leave_hand(play, indices);
+        if trading_card && !self.discarded_this_round && left.len() == 1 {
+            self.destroy(&left[0]);
+        }
+        self.discarded_this_round = true;
+    }
+
+    /// Removes a card from the deck for good.
+    fn destroy(&mut self, card: &PlayingCard) {
+        if let Some(i) = self.deck.iter().position(|c| same_card(c, card)) {
+            self.deck.swap_remove(i);
+        }
+        if let Some(i) = self.drawn.iter().position(|c| same_card(c, card)) {
+            self.drawn.swap_remove(i);
+        }
+    }
+
+    /// The cards still to be drawn this round.
+    pub fn remaining(&self) -> Vec<&PlayingCard> {
+        let mut drawn: Vec<&PlayingCard> = self.drawn.iter().collect();
+        self.deck
+            .iter()
+            .filter(|card| match drawn.iter().position(|c| same_card(c, card)) {
+                Some(i) => {
+                    drawn.swap_remove(i);
+                    false
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    /// The chance that drawing `draws` cards gets at least one matching
+    /// `wanted`.
+    pub fn chance_of(&self, draws: usize, wanted: impl Fn(&PlayingCard) -> bool) -> f64 {
+        let remaining = self.remaining();
+        let total = remaining.len();
+        let matching = remaining.iter().filter(|c| wanted(c)).count();
+        if draws >= total {
+            return if matching > 0 { 1.0 } else { 0.0 };
+        }
+        // One minus the chance every draw misses
+        let missing = (0..draws)
+            .map(|i| (total - matching).saturating_sub(i) as f64 / (total - i) as f64)
+            .product::<f64>();
+        1.0 - missing
+    }
+
+    fn leave_hand(&mut self, play: &Play, indices: &[usize]) -> Vec<PlayingCard> {
+        let mut left = Vec::new();
+        for card in indices
+            .iter()
+            .filter_map(|&i| play.hand().get(i)?.card.as_ref())
+        {
+            if let Some(i) = self.hand.iter().position(|c| same_card(c, card)) {
+                left.push(self.hand.swap_remove(i));
+            }
+        }
+        left
+    }
+}
+
+/// Cards count as the same if they share a rank and suit, since tarots can
+/// change everything else.
+fn same_card(a: &PlayingCard, b: &PlayingCard) -> bool {
+    a.rank == b.rank && a.suit == b.suit
+}
@@ -0,0 +1,86 @@
+use remotro::balatro::deck::{CardEdition, Enhancement, Seal};
+
+/// What a playing card modifier does each time the card triggers. Applied in
+/// the game's order: chips, then mult, then money, then xmult.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Effect {
+    pub chips: f64,
+    pub mult: f64,
+    pub xmult: f64,
+    pub money: f64,
+    /// Dollars paid out at the end of the round instead of straight away.
+    pub round_money: f64,
+    /// The effect only happens 1 in `odds` times.
+    pub odds: f64,
+}
+
+const NONE: Effect = Effect {
+    chips: 0.0,
+    mult: 0.0,
+    xmult: 1.0,
+    money: 0.0,
+    round_money: 0.0,
+    odds: 1.0,
+};
+
+/// The effects of a card's enhancement when it scores.
+pub fn played_enhancement(enhancement: Enhancement) -> &'static [Effect] {
+    match enhancement {
+        Enhancement::Bonus => &[Effect {
+            chips: 30.0,
+            ..NONE
+        }],
+        Enhancement::Mult => &[Effect { mult: 4.0, ..NONE }],
+        Enhancement::Glass => &[Effect { xmult: 2.0, ..NONE }],
+        Enhancement::Stone => &[Effect {
+            chips: 50.0,
+            ..NONE
+        }],
+        Enhancement::Lucky => &[
+            Effect {
+                mult: 20.0,
+                odds: 5.0,
+                ..NONE
+            },
+            Effect {
+                money: 20.0,
+                odds: 15.0,
+                ..NONE
+            },
+        ],
+        Enhancement::Wild | Enhancement::Steel | Enhancement::Gold => &[],
+    }
+}
+
+/// The effects of a card's enhancement while it's held in hand.
+pub fn held_enhancement(enhancement: Enhancement) -> &'static [Effect] {
+    match enhancement {
+        Enhancement::Steel => &[Effect { xmult: 1.5, ..NONE }],
+        Enhancement::Gold => &[Effect {
+            round_money: 3.0,
+            ..NONE
+        }],
+        _ => &[],
+    }
+}
+
+/// The effect of a card's edition when it scores.
+pub fn edition(edition: CardEdition) -> Effect {
+    match edition {
+        CardEdition::Foil => Effect {
+            chips: 50.0,
+            ..NONE
+        },
+        CardEdition::Holographic => Effect { mult: 10.0, ..NONE },
+        CardEdition::Polychrome => Effect { xmult: 1.5, ..NONE },
+    }
+}
+
+/// The effect of a card's seal when it scores. Red Seals retrigger the card
+/// instead, and Blue and Purple Seals only make consumables.
+pub fn seal(seal: &Seal) -> Effect {
+    match seal {
+        Seal::Gold => Effect { money: 3.0, ..NONE },
+        Seal::Red | Seal::Blue | Seal::Purple => NONE,
+    }
+}
@@ -0,0 +1,154 @@
+use remotro::balatro::{
+    consumables::Planet,
+    play::{
+        Play,
+        PokerHandKind::{self, *},
+    },
+};
+
+/// How a poker hand scores at its current level, and how often it has been
+/// played.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HandLevel {
+    pub kind: PokerHandKind,
+    pub level: u32,
+    pub chips: f64,
+    pub mult: f64,
+    /// Times played this run.
+    pub played: u32,
+    /// Times played this round.
+    pub played_round: u32,
+}
+
+/// The level of every poker hand in the run. Hands the game didn't report
+/// count as level 1 and never played.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HandLevels {
+    levels: Vec<HandLevel>,
+}
+
+impl HandLevels {
+    pub fn from_play(play: &Play) -> Self {
+        let levels = play
+            .run_info()
+            .poker_hands
+            .iter()
+            .map(|h| HandLevel {
+                kind: h.hand.kind,
+                level: h.hand.level as u32,
+                chips: h.hand.chips as f64,
+                mult: h.hand.mult as f64,
+                played: h.played as u32,
+                played_round: h.played_round as u32,
+            })
+            .collect();
+        Self { levels }
+    }
+
+    pub fn get(&self, kind: PokerHandKind) -> HandLevel {
+        self.levels
+            .iter()
+            .find(|h| h.kind == kind)
+            .copied()
+            .unwrap_or_else(|| base_level(kind))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HandLevel> {
+        self.levels.iter()
+    }
+
+    pub fn played(&self, kind: PokerHandKind) -> u32 {
+        self.get(kind).played
+    }
+
+    pub fn played_round(&self, kind: PokerHandKind) -> u32 {
+        self.get(kind).played_round
+    }
+
+    /// Whether playing `kind` next keeps it the most played hand of the run,
+    /// which is what resets Obelisk. Ties with another hand don't count.
+    pub fn is_most_played_after(&self, kind: PokerHandKind) -> bool {
+        let played = self.played(kind) + 1;
+        self.levels
+            .iter()
+            .all(|h| h.kind == kind || h.played < played)
+    }
+
+    /// Raises `kind` by `levels`, as using that many of its planet card would.
+    pub fn level_up(&mut self, kind: PokerHandKind, levels: u32) {
+        let (chips, mult) = level_up_amount(kind);
+        let index = match self.levels.iter().position(|h| h.kind == kind) {
+            Some(index) => index,
+            None => {
+                self.levels.push(base_level(kind));
+                self.levels.len() - 1
+            }
+        };
+        let hand = &mut self.levels[index];
+        hand.level += levels;
+        hand.chips += chips * f64::from(levels);
+        hand.mult += mult * f64::from(levels);
+    }
+}
+
+/// The chips and mult a planet card adds to `kind` per level.
+pub fn level_up_amount(kind: PokerHandKind) -> (f64, f64) {
+    match kind {
+        HighCard => (10.0, 1.0),
+        Pair => (15.0, 1.0),
+        TwoPair => (20.0, 1.0),
+        ThreeOfAKind => (20.0, 2.0),
+        Straight => (30.0, 3.0),
+        Flush => (15.0, 2.0),
+        FullHouse => (25.0, 2.0),
+        FourOfAKind => (30.0, 3.0),
+        StraightFlush => (40.0, 4.0),
+        FiveOfAKind => (35.0, 3.0),
+        FlushHouse => (40.0, 4.0),
+        FlushFive => (50.0, 3.0),
+    }
+}
+
+/// The hand a planet card levels up.
+pub fn planet_hand(planet: Planet) -> PokerHandKind {
+    match planet {
+        Planet::Pluto => HighCard,
+        Planet::Mercury => Pair,
+        Planet::Uranus => TwoPair,
+        Planet::Venus => ThreeOfAKind,
+        Planet::Saturn => Straight,
+        Planet::Jupiter => Flush,
+        Planet::Earth => FullHouse,
+        Planet::Mars => FourOfAKind,
+        Planet::Neptune => StraightFlush,
+        Planet::PlanetX => FiveOfAKind,
+        Planet::Ceres => FlushHouse,
+        Planet::Eris => FlushFive,
+    }
+}
+
+/// How `kind` scores at level 1.
+fn base_level(kind: PokerHandKind) -> HandLevel {
+    let (chips, mult) = match kind {
+        HighCard => (5.0, 1.0),
+        Pair => (10.0, 2.0),
+        TwoPair => (20.0, 2.0),
+        ThreeOfAKind => (30.0, 3.0),
+        Straight => (30.0, 4.0),
+        Flush => (35.0, 4.0),
+        FullHouse => (40.0, 4.0),
+        FourOfAKind => (60.0, 7.0),
+        StraightFlush => (100.0, 8.0),
+        FiveOfAKind => (120.0, 12.0),
+        FlushHouse => (140.0, 14.0),
+        FlushFive => (160.0, 16.0),
+    };
+    HandLevel {
+        kind,
+        level: 1,
+        chips,
+        mult,
+        played: 0,
+        played_round: 0,
+    }
+}
@@ -1,6 +1,22 @@
-use remotro::{Remotro, balatro::CurrentScreen::*};
+use remotro::{
+    Remotro,
+    balatro::{CurrentScreen::*, jokers::JokerKind, packs::PackCard},
+};
 
+mod benchmark;
+mod big_num;
+mod blind_choice;
+mod decks;
+mod discard;
+mod draw_pile;
+mod effects;
+mod hand_levels;
 mod play;
+mod round;
+mod search;
+mod shop;
+mod trace;
+mod verify;
 
 #[tokio::main]
 async fn main() {
@@ -19,30 +35,146 @@ async fn main() {
                 break;
             }
         };
+        // The last hand played, checked against the screen that follows it
+        let mut prediction: Option<verify::Prediction> = None;
+        // The cards left to draw, from the first hand of the run
+        let mut pile: Option<draw_pile::DrawPile> = None;
+        let mut form = blind_choice::Form::default();
+        let mut benchmark = benchmark::Benchmark::default();
         loop {
             match balatro.screen().await {
-                Ok(screen) => match screen {
-                    Menu(_menu) => {}
-                    SelectBlind(blinds) => {
-                        blinds.select().await.expect("message");
+                Ok(screen) => {
+                    if let Some(prediction) = prediction.take() {
+                        prediction.check(&screen);
                     }
-                    Play(/*mut*/ play) => {
-                        println!("Playing");
-                        //play = play.click(&[0]).await.expect("Something Failed");
-                        println!("{}", play::score_hand(&play));
-                        //let _ = play.play().await;
+                    match screen {
+                        Menu(_menu) => {
+                            pile = None;
+                            form = blind_choice::Form::default();
+                            benchmark = benchmark::Benchmark::default();
+                        }
+                        SelectBlind(blinds) => {
+                            if let Some(pile) = &mut pile {
+                                pile.new_round();
+                            }
+                            if let Some(tag) =
+                                blind_choice::tag_to_skip_for(&blinds, &form, &benchmark)
+                            {
+                                println!("Skipping for {tag:?}");
+                                blinds.skip().await.expect("message");
+                            } else {
+                                blinds.select().await.expect("message");
+                            }
+                        }
+                        Play(mut play) => {
+                            println!("Playing");
+                            let pile = pile.get_or_insert_with(|| {
+                                draw_pile::DrawPile::new(decks::DeckRules::for_deck(
+                                    play.run_info().deck,
+                                ))
+                            });
+                            pile.observe(&play);
+                            if let Some((i, cards)) = shop::card_change_to_use(&play) {
+                                println!("Using consumable {i} on {cards:?}");
+                                let clicks = search::clicks_for(&play, &cards);
+                                play = play.click(&clicks).await.expect("Something Failed");
+                                let _ = play.use_consumable(i).await;
+                                continue;
+                            }
+                            match round::plan_turn(&play, pile) {
+                                Some(round::Turn::Discard(discards)) => {
+                                    println!("Discarding {discards:?}");
+                                    let clicks = search::clicks_for(&play, &discards);
+                                    play = play.click(&clicks).await.expect("Something Failed");
+                                    let trading_card = play
+                                        .jokers()
+                                        .iter()
+                                        .any(|j| j.kind == JokerKind::TradingCard);
+                                    pile.discard(&play, &discards, trading_card);
+                                    let _ = play.discard().await;
+                                }
+                                Some(round::Turn::Play(cards, score)) => {
+                                    println!(
+                                        "Playing {cards:?} for {} ({} to {})",
+                                        score.expected(),
+                                        score.min(),
+                                        score.max()
+                                    );
+                                    form.record(&play, &score);
+                                    benchmark.record(&play, &cards);
+                                    if let Ok(Some(order)) = search::best_joker_order(&play, &cards)
+                                    {
+                                        for (from, to) in search::joker_moves(&order) {
+                                            play = play
+                                                .move_joker(from, to)
+                                                .await
+                                                .expect("Something Failed");
+                                        }
+                                    }
+                                    let clicks = search::clicks_for(&play, &cards);
+                                    play = play.click(&clicks).await.expect("Something Failed");
+                                    prediction = verify::Prediction::new(&play, &cards);
+                                    pile.play(&play, &cards, score.may_shatter());
+                                    let _ = play.play().await;
+                                }
+                                None => {}
+                            }
+                        }
+                        Shop(shop) => {
+                            if let Some(pile) = &mut pile {
+                                pile.new_round();
+                            }
+                            form.new_round();
+                            if let Some((i, planet)) = shop::consumable_to_use(&shop) {
+                                println!("Using consumable {i}");
+                                if let Some(planet) = planet {
+                                    benchmark.use_planet(planet);
+                                }
+                                shop.use_consumable(i).await.expect("Something Failed");
+                            } else if let Some(purchase) = shop::next_purchase(&shop, &benchmark) {
+                                println!("Buying {purchase:?}");
+                                match purchase {
+                                    shop::Purchase::Card(i) => {
+                                        shop.buy_main(i).await.expect("Something Failed");
+                                    }
+                                    shop::Purchase::Voucher(i) => {
+                                        shop.buy_voucher(i).await.expect("Something Failed");
+                                    }
+                                    shop::Purchase::Pack(i) => {
+                                        shop.buy_booster(i).await.expect("Something Failed");
+                                    }
+                                }
+                            } else {
+                                println!("Leaving the shop");
+                                shop.leave().await.expect("Something Failed");
+                            }
+                        }
+                        OpenPack(pack) => match shop::pack_choice(&pack, &benchmark) {
+                            Some(i) => {
+                                // Planets from packs are used as soon as they're picked
+                                if let Some(PackCard::Planet(planet)) = pack.cards().get(i) {
+                                    benchmark.use_planet(*planet);
+                                }
+                                pack.choose(i).await.expect("Something Failed");
+                            }
+                            None => {
+                                pack.skip().await.expect("Something Failed");
+                            }
+                        },
+                        GameOver(game) => {
+                            pile = None;
+                            form = blind_choice::Form::default();
+                            benchmark = benchmark::Benchmark::default();
+                            println!("{:?}",game.outcome());
+                            println!("{:?}",game.best_hand());
+                            println!("{:?}",game.most_played_hand());
+                            println!("{:?}",game.cards_discarded());
+                            println!("{:?}",game.cards_played());
+                            println!("{:?}",game.times_rerolled());
+                        }
+                        _ => {}
                     }
-                    Shop(_shop) => {}
-                    GameOver(game) => {
-                        println!("{:?}",game.outcome());
-                        println!("{:?}",game.best_hand());
-                        println!("{:?}",game.most_played_hand());
-                        println!("{:?}",game.cards_discarded());
-                        println!("{:?}",game.cards_played());
-                        println!("{:?}",game.times_rerolled());
-                    }
-                    _ => {}
-                },
+                }
                 Err(e) => {
                     println!("{e}");
                     break;
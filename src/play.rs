@@ -1,30 +1,38 @@
-use remotro::balatro::deck::Enhancement;
+use std::{fmt, ptr, rc::Rc};
+
 use remotro::balatro::{
+    blinds::BossBlind,
     deck::{
-        CardEdition::*,
-        Enhancement::*,
+        Enhancement::{self, *},
         PlayingCard,
         Rank::{self, *},
         Seal::*,
-        Suit::*,
+        Suit::{self, *},
     },
     hud::Hud,
     jokers::{
-        JokerEdition,
+        self, JokerEdition,
         JokerKind::{self, *},
     },
-    menu::Deck::Plasma,
     play::{
         Play,
         PokerHandKind::{self, *},
     },
 };
 
+use crate::{
+    big_num::BigNum,
+    decks::DeckRules,
+    effects::{self, Effect},
+    hand_levels::{HandLevels, level_up_amount},
+    trace::{Trace, Tracer},
+};
+
 fn get_scored_cards<'a>(
     selected: &mut Vec<&'a PlayingCard>,
     hand_type: PokerHandKind,
-    shortcut: bool,
-    // TODO: Add support for smeared joker
+    modifiers: HandModifiers,
+    debuffed: &[&PlayingCard],
 ) -> Vec<&'a PlayingCard> {
     const FIVE_CARD_HANDS: [PokerHandKind; 4] = [FiveOfAKind, FlushFive, FlushHouse, FullHouse];
     // Hands that always require 5 cards to exist
@@ -32,11 +40,9 @@ fn get_scored_cards<'a>(
     if FIVE_CARD_HANDS.contains(&hand_type) {
         return selected.clone();
     }
-    for card in &mut *selected {
-        if card.enhancement == Some(Stone) {
-            scored.push(card);
-        }
-    }
+    // Stone cards always score, but they have no rank to make the hand with
+    scored.extend(selected.iter().filter(|c| c.enhancement == Some(Stone)));
+    selected.retain(|c| c.enhancement != Some(Stone));
     selected.sort_unstable_by_key(|c| c.rank);
     match hand_type {
         HighCard => {
@@ -73,452 +79,1377 @@ fn get_scored_cards<'a>(
                 scored.extend_from_slice(&selected[j..j + 2]);
             }
         }
-        Straight => {
-            if selected.windows(2).all(|w| w[1].rank == w[0].rank.next()) {
-                scored.extend_from_slice(selected); // 5 card Straight
-            } else if shortcut
-                && selected
-                    .windows(2)
-                    .all(|w| w[1].rank == w[0].rank.next().next())
-            {
-                scored.extend_from_slice(selected); // 5 card Straight with shortcut
-            } else if let Some(i) = selected
-                .windows(4)
-                .position(|w| w.windows(2).all(|w| w[1].rank == w[0].rank.next()))
-            {
-                scored.extend_from_slice(&selected[i..i + 4]); // 4 card straight (Four Fingers)
-            } else if shortcut
-                && let Some(i) = selected
-                    .windows(4)
-                    .position(|w| w.windows(2).all(|w| w[1].rank <= w[0].rank.next().next()))
-            {
-                scored.extend_from_slice(&selected[i..i + 4]); // 4 card straight with shortcut
+        Straight => scored.extend(find_straight(selected, modifiers)),
+        Flush => scored.extend(find_flush(selected, modifiers, debuffed)),
+        StraightFlush => {
+            // Like the game, the straight and the flush don't have to be made
+            // of the same cards once Four Fingers is involved, so every card
+            // in either of them scores.
+            let flush = find_flush(selected, modifiers, debuffed);
+            let straight = find_straight(selected, modifiers);
+            scored.extend_from_slice(&flush);
+            scored.extend(
+                straight
+                    .into_iter()
+                    .filter(|c| !flush.iter().any(|f| ptr::eq(*f, *c))),
+            );
+        }
+        _ => unreachable!(),
+    }
+    scored
+}
+
+/// Jokers that change which cards form a poker hand.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HandModifiers {
+    pub four_fingers: bool,
+    pub shortcut: bool,
+    pub smeared: bool,
+    pub splash: bool,
+}
+
+impl HandModifiers {
+    pub fn from_jokers<'a>(jokers: impl IntoIterator<Item = &'a JokerKind>) -> Self {
+        let mut modifiers = Self::default();
+        for joker in jokers {
+            match joker {
+                FourFingers => modifiers.four_fingers = true,
+                Shortcut => modifiers.shortcut = true,
+                Smeared => modifiers.smeared = true,
+                Splash => modifiers.splash = true,
+                _ => {}
             }
         }
-        Flush => 'block: {
-            if selected.iter().all(|c| c.suit == selected[0].suit) {
-                scored.extend_from_slice(selected);
-                break 'block;
+        modifiers
+    }
+
+    /// How many cards a Flush or Straight needs.
+    fn run_length(self) -> usize {
+        if self.four_fingers { 4 } else { 5 }
+    }
+}
+
+/// Works out the poker hand formed by `cards` and which of them score,
+/// without asking the game. `debuffed` are the cards the boss blind has
+/// debuffed.
+pub fn evaluate_hand<'a>(
+    cards: &[&'a PlayingCard],
+    modifiers: HandModifiers,
+    debuffed: &[&PlayingCard],
+) -> (PokerHandKind, Vec<&'a PlayingCard>) {
+    let kind = classify_hand(cards, modifiers, debuffed);
+    let mut scored = if modifiers.splash {
+        cards.to_vec()
+    } else {
+        get_scored_cards(&mut cards.to_vec(), kind, modifiers, debuffed)
+    };
+    // Cards score left to right in the order they were played
+    scored.sort_by_key(|s| cards.iter().position(|c| ptr::eq(*c, *s)));
+    (kind, scored)
+}
+
+/// Classifies the poker hand formed by `cards` without asking the game.
+pub fn classify_hand(
+    cards: &[&PlayingCard],
+    modifiers: HandModifiers,
+    debuffed: &[&PlayingCard],
+) -> PokerHandKind {
+    let mut ranked: Vec<&PlayingCard> = cards
+        .iter()
+        .copied()
+        .filter(|c| c.enhancement != Some(Stone))
+        .collect();
+    ranked.sort_unstable_by_key(|c| c.rank);
+    let mut counts: Vec<usize> = ranked
+        .chunk_by(|a, b| a.rank == b.rank)
+        .map(<[_]>::len)
+        .collect();
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    let flush = !find_flush(&ranked, modifiers, debuffed).is_empty();
+    let straight = !find_straight(&ranked, modifiers).is_empty();
+    match (counts.as_slice(), flush) {
+        ([5], true) => FlushFive,
+        ([3, 2], true) => FlushHouse,
+        ([5], false) => FiveOfAKind,
+        _ if straight && flush => StraightFlush,
+        ([4, ..], _) => FourOfAKind,
+        ([3, 2], false) => FullHouse,
+        _ if flush => Flush,
+        _ if straight => Straight,
+        ([3, ..], _) => ThreeOfAKind,
+        ([2, 2, ..], _) => TwoPair,
+        ([2, ..], _) => Pair,
+        _ => HighCard,
+    }
+}
+
+/// Whether `card` counts as `suit`. Wild cards count as every suit unless
+/// they're `debuffed`, Smeared Joker merges Hearts with Diamonds and Spades
+/// with Clubs, and Stone cards have no suit at all.
+fn is_suit(card: &PlayingCard, suit: Suit, smeared: bool, debuffed: bool) -> bool {
+    match card.enhancement {
+        Some(Stone) => return false,
+        Some(Wild) if !debuffed => return true,
+        _ => {}
+    }
+    card.suit == suit
+        || smeared
+            && matches!(
+                (card.suit, suit),
+                (Hearts | Diamonds, Hearts | Diamonds) | (Spades | Clubs, Spades | Clubs)
+            )
+}
+
+/// Whether each group of suits can be matched to a different card in
+/// `cards`, as Flower Pot and Seeing Double require.
+fn covers_suits(
+    cards: &[&PlayingCard],
+    groups: &[&[Suit]],
+    smeared: bool,
+    debuffed: &[&PlayingCard],
+) -> bool {
+    let Some((group, rest)) = groups.split_first() else {
+        return true;
+    };
+    cards.iter().enumerate().any(|(i, card)| {
+        group
+            .iter()
+            .any(|&suit| is_suit(card, suit, smeared, contains_card(debuffed, card)))
+            && {
+                let mut remaining = cards.to_vec();
+                remaining.remove(i);
+                covers_suits(&remaining, rest, smeared, debuffed)
             }
-            selected.sort_unstable_by_key(|a| a.suit);
-            if let Some(i) = selected
-                .windows(4)
-                .position(|w| w.iter().all(|c| c.suit == w[0].suit))
-            {
-                scored.extend_from_slice(&selected[i..i + 4]);
+    })
+}
+
+/// The cards making up a Flush, or nothing if there isn't one.
+fn find_flush<'a>(
+    cards: &[&'a PlayingCard],
+    modifiers: HandModifiers,
+    debuffed: &[&PlayingCard],
+) -> Vec<&'a PlayingCard> {
+    for suit in [Spades, Hearts, Clubs, Diamonds] {
+        let matching: Vec<&PlayingCard> = cards
+            .iter()
+            .copied()
+            .filter(|c| is_suit(c, suit, modifiers.smeared, contains_card(debuffed, c)))
+            .collect();
+        if matching.len() >= modifiers.run_length() {
+            return matching;
+        }
+    }
+    Vec::new()
+}
+
+/// The cards making up a Straight, or nothing if there isn't one.
+fn find_straight<'a>(cards: &[&'a PlayingCard], modifiers: HandModifiers) -> Vec<&'a PlayingCard> {
+    let cards: Vec<&PlayingCard> = cards
+        .iter()
+        .copied()
+        .filter(|c| c.enhancement != Some(Stone))
+        .collect();
+    let has = |value: u8| cards.iter().any(|c| rank_matches(c.rank, value));
+    // Aces sit at both ends, but the scan never carries a run from King back
+    // round to Two, so straights can't wrap.
+    let mut best: Vec<u8> = Vec::new();
+    let mut run: Vec<u8> = Vec::new();
+    let mut skipped = false;
+    for value in 1..=14 {
+        if has(value) {
+            run.push(value);
+            skipped = false;
+        } else if modifiers.shortcut && !skipped && !run.is_empty() {
+            skipped = true;
+        } else {
+            run.clear();
+            skipped = false;
+        }
+        if run.len() > best.len() {
+            best.clone_from(&run);
+        }
+    }
+    if best.len() < modifiers.run_length() {
+        return Vec::new();
+    }
+    cards
+        .into_iter()
+        .filter(|c| best.iter().any(|&v| rank_matches(c.rank, v)))
+        .collect()
+}
+
+/// Whether `card` is one of `cards` itself, rather than just an equal card.
+fn contains_card(cards: &[&PlayingCard], card: &PlayingCard) -> bool {
+    cards.iter().any(|c| ptr::eq(*c, card))
+}
+
+/// Where `rank` sits in a straight, with Ace high.
+fn rank_value(rank: Rank) -> u8 {
+    match rank {
+        Two => 2,
+        Three => 3,
+        Four => 4,
+        Five => 5,
+        Six => 6,
+        Seven => 7,
+        Eight => 8,
+        Nine => 9,
+        Ten => 10,
+        Jack => 11,
+        Queen => 12,
+        King => 13,
+        Ace => 14,
+    }
+}
+
+/// Whether `rank` can fill position `value` of a straight, counting Ace as
+/// both 1 and 14.
+fn rank_matches(rank: Rank, value: u8) -> bool {
+    rank_value(rank) == value || rank == Ace && value == 1
+}
+
+/// Why a hand couldn't be scored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScoreError {
+    /// A joker whose effect on the score isn't modelled, so any score would
+    /// be wrong.
+    UnsupportedJoker(String),
+}
+
+impl fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedJoker(joker) => write!(f, "Can't score hands with {joker} yet"),
+        }
+    }
+}
+
+impl std::error::Error for ScoreError {}
+
+/// Scores the cards the player currently has selected.
+pub fn score_hand(play: &Play) -> Result<ScoreDistribution, ScoreError> {
+    let selected: Vec<usize> = play
+        .hand()
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.selected)
+        .map(|(i, _)| i)
+        .collect();
+    score(&GameState::from_play(play, &selected))
+}
+
+/// A snapshot of everything scoring depends on, so hands can be scored
+/// without them being selected in game.
+#[derive(Clone, Debug)]
+pub struct GameState<'a> {
+    pub selected: Vec<&'a PlayingCard>,
+    pub held: Vec<&'a PlayingCard>,
+    /// Cards in `selected` or `held` that are debuffed and so do nothing.
+    pub debuffed: Vec<&'a PlayingCard>,
+    /// In the order they sit in, which is the order they trigger in.
+    pub jokers: Vec<&'a jokers::Joker>,
+    /// Shared, since every hand scored in a turn sees the same levels.
+    pub hand_levels: Rc<HandLevels>,
+    pub money: i64,
+    pub hands: u32,
+    pub discards: u32,
+    pub rules: DeckRules,
+    /// The boss blind being played against, if any.
+    pub boss: Option<BossBlind>,
+}
+
+impl<'a> GameState<'a> {
+    /// Snapshots `play` as if the cards at `indices` were the ones played.
+    /// To score several selections from the same hand, snapshot it once and
+    /// [`select`](Self::select) from that.
+    pub fn from_play(play: &'a Play, indices: &[usize]) -> Self {
+        // Chicot disables every boss blind's ability
+        let chicot = play
+            .jokers()
+            .iter()
+            .any(|j| matches!(j.kind, Chicot { .. }));
+        Self {
+            selected: Vec::new(),
+            held: Vec::new(),
+            debuffed: Vec::new(),
+            jokers: play.jokers().iter().collect(),
+            hand_levels: Rc::new(HandLevels::from_play(play)),
+            money: play.money().into(),
+            hands: play.hands().into(),
+            discards: play.discards().into(),
+            rules: DeckRules::for_deck(play.run_info().deck),
+            boss: play.blind().boss.filter(|_| !chicot),
+        }
+        .select(play, indices)
+    }
+
+    /// The same state with the cards at `indices` of `play.hand()` played and
+    /// the rest held, regardless of what is currently selected in game.
+    pub fn select(&self, play: &'a Play, indices: &[usize]) -> Self {
+        let mut selected = Vec::new();
+        let mut held = Vec::new();
+        for (i, card) in play.hand().iter().enumerate() {
+            if let Some(card_data) = &card.card {
+                if indices.contains(&i) {
+                    selected.push(card_data);
+                } else {
+                    held.push(card_data);
+                }
             }
         }
-        StraightFlush => 'block: {
-            if selected.windows(2).all(|w| w[1].rank <= w[0].rank.next())
-                && selected.iter().all(|c| c.suit == selected[0].suit)
-            {
-                scored.extend_from_slice(selected);
-                break 'block;
+        self.clone().with_cards(selected, held)
+    }
+
+    /// The same state with different cards played and held, such as ones that
+    /// haven't been drawn yet.
+    pub fn with_cards(self, selected: Vec<&'a PlayingCard>, held: Vec<&'a PlayingCard>) -> Self {
+        let modifiers = HandModifiers::from_jokers(self.jokers.iter().map(|j| &j.kind));
+        let pareidolia = self.jokers.iter().any(|j| j.kind == Pareidolia);
+        let debuffed = selected
+            .iter()
+            .chain(&held)
+            .copied()
+            .filter(|c| {
+                self.boss
+                    .is_some_and(|b| boss_debuffs(b, c, modifiers.smeared, pareidolia))
+            })
+            .collect();
+        Self {
+            selected,
+            held,
+            debuffed,
+            ..self
+        }
+    }
+
+    fn is_debuffed(&self, card: &PlayingCard) -> bool {
+        contains_card(&self.debuffed, card)
+    }
+}
+
+/// What playing a hand is worth.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Score {
+    pub chips: BigNum,
+    pub mult: BigNum,
+    /// Dollars earned while the hand scores.
+    pub money: f64,
+    /// Dollars the cards held in hand will earn at the end of the round.
+    pub round_money: f64,
+    /// Glass cards that shatter after scoring, as a bit for each position in
+    /// the played cards.
+    pub shattered: u8,
+}
+
+impl Score {
+    /// The chips the hand adds to the round score.
+    pub fn total(&self) -> BigNum {
+        self.chips * self.mult
+    }
+}
+
+/// Every way a hand can score once chance-based effects play out, and how
+/// likely each one is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScoreDistribution {
+    pub outcomes: Vec<(f64, Score)>,
+}
+
+impl ScoreDistribution {
+    pub fn min(&self) -> BigNum {
+        self.totals()
+            .reduce(|a, b| if b < a { b } else { a })
+            .unwrap_or(BigNum::ZERO)
+    }
+
+    pub fn max(&self) -> BigNum {
+        self.totals().fold(BigNum::ZERO, BigNum::max)
+    }
+
+    pub fn expected(&self) -> BigNum {
+        self.outcomes.iter().map(|(p, s)| s.total() * *p).sum()
+    }
+
+    pub fn expected_money(&self) -> f64 {
+        self.outcomes
+            .iter()
+            .map(|(p, s)| p * (s.money + s.round_money))
+            .sum()
+    }
+
+    /// The chance the hand scores at least `target`.
+    pub fn chance_to_reach(&self, target: BigNum) -> f64 {
+        self.outcomes
+            .iter()
+            .filter(|(_, s)| s.total() >= target)
+            .map(|(p, _)| p)
+            .sum()
+    }
+
+    /// A bit for each played card that shatters in any outcome.
+    pub fn may_shatter(&self) -> u8 {
+        self.outcomes
+            .iter()
+            .fold(0, |bits, (_, s)| bits | s.shattered)
+    }
+
+    fn totals(&self) -> impl Iterator<Item = BigNum> {
+        self.outcomes.iter().map(|(_, s)| s.total())
+    }
+}
+
+/// Past this many outcomes, hands are sampled instead of fully enumerated.
+const MAX_OUTCOMES: usize = 4096;
+const SAMPLES: usize = 1024;
+
+/// Decides how each chance-based effect plays out while a hand scores, either
+/// replaying a fixed list of choices or sampling them.
+#[derive(Clone, Debug, Default)]
+struct Rolls {
+    choices: Vec<usize>,
+    weights: Vec<Vec<f64>>,
+    seed: Option<u64>,
+    /// Pick the likeliest outcome of new choices instead of the first.
+    likeliest: bool,
+}
+
+impl Rolls {
+    /// Picks one of several outcomes with the given weights.
+    fn choose(&mut self, weights: Vec<f64>) -> usize {
+        let i = self.weights.len();
+        let choice = match (self.choices.get(i), &mut self.seed) {
+            (Some(&choice), _) => choice,
+            (None, Some(seed)) => {
+                *seed ^= *seed << 13;
+                *seed ^= *seed >> 7;
+                *seed ^= *seed << 17;
+                let mut x = (*seed >> 11) as f64 / (1u64 << 53) as f64;
+                weights
+                    .iter()
+                    .position(|w| {
+                        x -= w;
+                        x < 0.0
+                    })
+                    .unwrap_or(weights.len() - 1)
             }
-            selected.sort_by_key(|c| c.suit); // TODO; need to be able to detect
+            (None, None) if self.likeliest => weights
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map_or(0, |(i, _)| i),
+            (None, None) => 0,
+        };
+        self.weights.push(weights);
+        if i >= self.choices.len() {
+            self.choices.push(choice);
         }
-        _ => unreachable!(),
+        choice
+    }
+
+    /// Whether an effect with `chance` of happening happens.
+    fn roll(&mut self, chance: f64) -> bool {
+        self.choose(vec![1.0 - chance, chance]) == 1
+    }
+
+    fn probability(&self) -> f64 {
+        self.choices
+            .iter()
+            .zip(&self.weights)
+            .map(|(&c, w)| w[c])
+            .product()
     }
-    scored
 }
 
-pub fn score_hand(play: &Play) -> f64 {
-    let mut selected: Vec<&PlayingCard> = Vec::new();
-    let mut hand: Vec<&PlayingCard> = Vec::new();
-    for card in play.hand() {
-        if let Some(card_data) = &card.card {
-            if card.selected {
-                selected.push(card_data);
-            } else {
-                if let Some(Red) = card_data.seal {
-                    hand.push(card_data);
+/// Scores playing `state.selected` while holding `state.held`, covering
+/// every way its chance-based effects can play out.
+pub fn score(state: &GameState) -> Result<ScoreDistribution, ScoreError> {
+    check_supported(state)?;
+    let mut outcomes = Vec::new();
+    let mut pending = vec![Vec::new()];
+    while let Some(choices) = pending.pop() {
+        if outcomes.len() + pending.len() >= MAX_OUTCOMES {
+            return Ok(sample(state));
+        }
+        let mut rolls = Rolls {
+            choices,
+            ..Rolls::default()
+        };
+        let fixed = rolls.choices.len();
+        let score = score_outcome(state, &mut rolls, &mut Tracer::default());
+        // Branch on every alternative of each choice made for the first time
+        for i in fixed..rolls.weights.len() {
+            for alternative in 1..rolls.weights[i].len() {
+                if rolls.weights[i][alternative] > 0.0 {
+                    let mut choices = rolls.choices[..i].to_vec();
+                    choices.push(alternative);
+                    pending.push(choices);
                 }
-                hand.push(card_data);
             }
         }
+        let probability = rolls.probability();
+        if probability > 0.0 {
+            outcomes.push((probability, score));
+        }
     }
+    Ok(ScoreDistribution { outcomes })
+}
+
+/// Scores the likeliest way the hand can play out, recording every step that
+/// changes the chips or mult.
+pub fn trace(state: &GameState) -> Result<(Score, Trace), ScoreError> {
+    check_supported(state)?;
+    let mut rolls = Rolls {
+        likeliest: true,
+        ..Rolls::default()
+    };
+    let mut tracer = Tracer::enabled();
+    let score = score_outcome(state, &mut rolls, &mut tracer);
+    Ok((score, tracer.finish()))
+}
+
+fn check_supported(state: &GameState) -> Result<(), ScoreError> {
+    match state.jokers.iter().find(|j| !is_supported(&j.kind)) {
+        Some(joker) => Err(ScoreError::UnsupportedJoker(format!("{:?}", joker.kind))),
+        None => Ok(()),
+    }
+}
+
+fn sample(state: &GameState) -> ScoreDistribution {
+    let outcomes = (1..=SAMPLES as u64)
+        .map(|seed| {
+            let mut rolls = Rolls {
+                seed: Some(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15)),
+                ..Rolls::default()
+            };
+            let score = score_outcome(state, &mut rolls, &mut Tracer::default());
+            (1.0 / SAMPLES as f64, score)
+        })
+        .collect();
+    ScoreDistribution { outcomes }
+}
+
+/// Scores a single way the hand's chance-based effects can play out.
+fn score_outcome(state: &GameState, rolls: &mut Rolls, tracer: &mut Tracer) -> Score {
+    let selected = &state.selected;
+    let hand = &state.held;
     if selected.is_empty() {
-        return 0.0
+        return Score::default();
     }
-    let mut chips = play.poker_hand().unwrap().chips as f64;
-    let mut mult = play.poker_hand().unwrap().mult as f64;
-    let scored = if play.jokers().iter().any(|joker| joker.kind == Splash) {
-        selected.into_iter().collect()
-    } else {
-        get_scored_cards(
-            &mut selected,
-            play.poker_hand().unwrap().kind,
-            play.jokers().iter().any(|j| j.kind == FourFingers),
-        )
+    let modifiers = HandModifiers::from_jokers(state.jokers.iter().map(|j| &j.kind));
+    let copies = resolve_jokers(&state.jokers);
+    let kinds: Vec<&JokerKind> = copies.iter().flatten().copied().collect();
+    let (hand_kind, scored) = evaluate_hand(selected, modifiers, &state.debuffed);
+    let matadors = kinds.iter().filter(|k| ***k == Matador).count() as f64;
+    if !boss_allows(state, hand_kind) {
+        // The hand scores nothing, but it did trigger the boss
+        let money = 8.0 * matadors;
+        tracer.record(
+            || "Matador".to_string(),
+            (BigNum::ZERO, BigNum::ZERO, 0.0),
+            (BigNum::ZERO, BigNum::ZERO, money),
+        );
+        return Score {
+            money,
+            ..Score::default()
+        };
+    }
+    let boss_triggered =
+        state.boss == Some(BossBlind::TheFlint) || selected.iter().any(|c| state.is_debuffed(c));
+    let level = state.hand_levels.get(hand_kind);
+    let (mut chips, mut mult) = (BigNum::from(level.chips), BigNum::from(level.mult));
+    tracer.record(
+        || format!("{hand_kind:?}"),
+        (BigNum::ZERO, BigNum::ZERO, 0.0),
+        (chips, mult, 0.0),
+    );
+    let mut money = 0.0;
+    let mut round_money = 0.0;
+    let mut shattered = 0;
+    // Oops! All 6s doubles every listed probability
+    let oops = state.jokers.iter().filter(|j| j.kind == Oops).count();
+    let odds = |n: f64| (2f64.powi(oops as i32) / n).min(1.0);
+    for kind in &kinds {
+        if **kind == SpaceJoker && rolls.roll(odds(4.0)) {
+            let before = (chips, mult, money + round_money);
+            let (level_chips, level_mult) = level_up_amount(hand_kind);
+            chips += level_chips;
+            mult += level_mult;
+            tracer.record(
+                || "SpaceJoker".to_string(),
+                before,
+                (chips, mult, money + round_money),
+            );
+        }
+    }
+    if state.boss == Some(BossBlind::TheFlint) {
+        let before = (chips, mult, money + round_money);
+        chips = (chips * 0.5 + 0.5).floor();
+        mult = (mult * 0.5 + 0.5).floor().max(BigNum::from(1.0));
+        tracer.record(
+            || "TheFlint".to_string(),
+            before,
+            (chips, mult, money + round_money),
+        );
+    }
+    let played_count = selected.len();
+    let smeared = modifiers.smeared;
+    let pareidolia = state.jokers.iter().any(|j| j.kind == Pareidolia);
+    // Debuffed cards don't count as face cards
+    let is_scored_face = |c: &PlayingCard| is_face(c, pareidolia) && !state.is_debuffed(c);
+    let first_face = scored.iter().position(|&c| is_scored_face(c));
+    // Midas Mask turns scored face cards Gold before any of them score
+    let midas = kinds.iter().position(|k| matches!(k, MidasMask { .. }));
+    let enhancement = |c: &PlayingCard| {
+        if midas.is_some() && is_scored_face(c) {
+            Some(Enhancement::Gold)
+        } else {
+            c.enhancement
+        }
     };
+    // Vampire eats the enhancements of scored cards before they can trigger,
+    // but only eats that Gold when Midas Mask is to its left
+    let vampired = kinds.iter().any(|k| matches!(k, Vampire { .. }));
+    let midas_first =
+        midas.is_some_and(|m| !kinds[..m].iter().any(|k| matches!(k, Vampire { .. })));
+    let vampire_meals = scored
+        .iter()
+        .filter(|&&c| {
+            let eaten = if midas_first {
+                enhancement(c)
+            } else {
+                c.enhancement
+            };
+            eaten.is_some() && !state.is_debuffed(c)
+        })
+        .count();
     // Cards that are played
-    for card in &scored {
-        if let Some(e) = card.enhancement
-            && !play
-                .jokers()
-                .iter()
-                .any(|j| matches!(j.kind, Vampire { .. }))
-            {
-                match e {
-                    Bonus => chips += 30.0,
-                    Glass => mult *= 2.0,
-                    Mult => mult += 4.0,
-                    Lucky => mult += 20.0,
-                    Stone => chips += 50.0,
-                    _ => {}
-                }
+    for (i, card) in scored.iter().enumerate() {
+        if state.is_debuffed(card) {
+            continue;
+        }
+        let card_enhancement = enhancement(card).filter(|_| !vampired);
+        let mut card_effects: Vec<Effect> = Vec::new();
+        card_effects
+            .extend_from_slice(card_enhancement.map_or(&[][..], effects::played_enhancement));
+        card_effects.extend(card.seal.as_ref().map(effects::seal));
+        card_effects.extend(card.edition.map(effects::edition));
+        for trigger in 0..=played_retriggers(state, &kinds, card, i == 0, pareidolia) {
+            let name = || card_name(card, trigger);
+            let before = (chips, mult, money + round_money);
+            // A Stone card that Vampire ate has its rank back
+            if card_enhancement != Some(Stone) {
+                chips += get_chips_from_rank(card.rank);
             }
-        if let Some(e) = card.edition {
-            match e {
-                Foil => chips += 50.0,
-                Holographic => chips += 10.0,
-                Polychrome => mult *= 1.5,
+            for effect in &card_effects {
+                if effect.odds <= 1.0 || rolls.roll(odds(effect.odds)) {
+                    apply_effect(effect, &mut chips, &mut mult, &mut money, &mut round_money);
+                }
             }
-        }
-        for joker in play.jokers() {
-            match joker.kind {
-                GreedyJoker => {
-                    if card.suit == Diamonds {
-                        mult += 3.0
+            tracer.record(name, before, (chips, mult, money + round_money));
+            for kind in &kinds {
+                let before = (chips, mult, money + round_money);
+                match **kind {
+                    GreedyJoker => {
+                        if is_suit(card, Diamonds, smeared, false) {
+                            mult += 3.0
+                        }
                     }
-                }
-                LustyJoker => {
-                    if card.suit == Hearts {
-                        mult += 3.0
+                    LustyJoker => {
+                        if is_suit(card, Hearts, smeared, false) {
+                            mult += 3.0
+                        }
                     }
-                }
-                WrathfulJoker => {
-                    if card.suit == Spades {
-                        mult += 3.0
+                    WrathfulJoker => {
+                        if is_suit(card, Spades, smeared, false) {
+                            mult += 3.0
+                        }
                     }
-                }
-                GluttenousJoker => {
-                    if card.suit == Clubs {
-                        mult += 3.0
+                    GluttenousJoker => {
+                        if is_suit(card, Clubs, smeared, false) {
+                            mult += 3.0
+                        }
                     }
-                }
-                EightBall { .. } => todo!(),
-                Dusk => {
-                    if play.hands() == 1 {
-                        todo!()
+                    // Only creates a Tarot card, which doesn't change the score
+                    EightBall { .. } => {}
+                    Business { .. } => {
+                        if is_face(card, pareidolia) && rolls.roll(odds(2.0)) {
+                            money += 2.0
+                        }
                     }
-                }
-                Fibonacci => match card.rank {
-                    Ace | Two | Three | Five | Eight => mult += 8.0,
-                    _ => {}
-                },
-                ScaryFace => match card.rank {
-                    Jack | Queen | King => chips += 30.0,
-                    _ => {}
-                },
-                Hack => todo!(),
-                EvenSteven => match card.rank {
-                    Two | Four | Six | Eight | Ten => mult += 4.0,
-                    _ => {}
-                },
-                OddTodd => match card.rank {
-                    Ace | Three | Five | Seven | Nine => chips += 31.0,
-                    _ => {}
-                },
-                Scholar => {
-                    if card.rank == Ace {
-                        mult += 4.0;
-                        chips += 20.0
+                    Ticket => {
+                        if card.enhancement == Some(Enhancement::Gold) {
+                            money += 4.0
+                        }
                     }
-                }
-                Business { .. } => match card.rank {
-                    Jack | Queen | King => todo!(),
-                    _ => {}
-                },
-                Ancient { suit } => {
-                    if card.suit == suit {
-                        mult *= 1.5;
+                    RoughGem => {
+                        if is_suit(card, Diamonds, smeared, false) {
+                            money += 1.0
+                        }
                     }
-                }
-                WalkieTalkie => match card.rank {
-                    Four | Ten => {
-                        chips += 10.0;
-                        mult += 4.0
+                    Fibonacci => match card.rank {
+                        Ace | Two | Three | Five | Eight => mult += 8.0,
+                        _ => {}
+                    },
+                    ScaryFace => {
+                        if is_face(card, pareidolia) {
+                            chips += 30.0
+                        }
                     }
-                    _ => {}
-                },
-                Smiley => match card.rank {
-                    Jack | Queen | King => mult += 5.0,
-                    _ => {}
-                },
-                Ticket => {
-                    if card.enhancement == Some(Enhancement::Gold) {
-                        todo!()
+                    EvenSteven => match card.rank {
+                        Two | Four | Six | Eight | Ten => mult += 4.0,
+                        _ => {}
+                    },
+                    OddTodd => match card.rank {
+                        Ace | Three | Five | Seven | Nine => chips += 31.0,
+                        _ => {}
+                    },
+                    Scholar => {
+                        if card.rank == Ace {
+                            mult += 4.0;
+                            chips += 20.0
+                        }
                     }
-                }
-                RoughGem => {
-                    if card.suit == Diamonds {
-                        todo!()
+                    Ancient { suit } => {
+                        if is_suit(card, suit, smeared, false) {
+                            mult *= 1.5;
+                        }
                     }
-                }
-                Bloodstone { .. } => {
-                    if card.suit == Hearts {
-                        mult *= 1.5;
+                    WalkieTalkie => match card.rank {
+                        Four | Ten => {
+                            chips += 10.0;
+                            mult += 4.0
+                        }
+                        _ => {}
+                    },
+                    Smiley => {
+                        if is_face(card, pareidolia) {
+                            mult += 5.0
+                        }
                     }
-                }
-                Arrowhead => {
-                    if card.suit == Spades {
-                        chips += 50.0
+                    Bloodstone { .. } => {
+                        if is_suit(card, Hearts, smeared, false) && rolls.roll(odds(2.0)) {
+                            mult *= 1.5;
+                        }
                     }
-                }
-                OnyxAgate => {
-                    if card.suit == Clubs {
-                        mult += 7.0
+                    Arrowhead => {
+                        if is_suit(card, Spades, smeared, false) {
+                            chips += 50.0
+                        }
                     }
-                }
-                Idol { rank, suit } => {
-                    if card.rank == rank && card.suit == suit {
-                        mult *= 2.0
+                    OnyxAgate => {
+                        if is_suit(card, Clubs, smeared, false) {
+                            mult += 7.0
+                        }
+                    }
+                    Idol { rank, suit } => {
+                        if card.rank == rank && is_suit(card, suit, smeared, false) {
+                            mult *= 2.0
+                        }
+                    }
+                    Triboulet => match card.rank {
+                        Queen | King => mult *= 2.0,
+                        _ => {}
+                    },
+                    Photograph => {
+                        if first_face == Some(i) {
+                            mult *= 2.0
+                        }
                     }
-                }
-                Triboulet => match card.rank {
-                    Queen | King => mult *= 2.0,
                     _ => {}
-                },
-                _ => {}
+                }
+                tracer.record(
+                    || format!("{kind:?} on {}", name()),
+                    before,
+                    (chips, mult, money + round_money),
+                );
             }
         }
-        chips += get_chips_from_rank(card.rank);
     }
+    for (i, &card) in selected.iter().enumerate() {
+        if contains_card(&scored, card)
+            && enhancement(card) == Some(Glass)
+            && !vampired
+            && !state.is_debuffed(card)
+            && rolls.roll(odds(4.0))
+        {
+            shattered |= 1 << i;
+        }
+    }
+    // Raised Fist only counts the rightmost of the lowest ranked cards, even
+    // when that one is debuffed
+    let lowest_held = hand
+        .iter()
+        .rev()
+        .filter(|c| c.enhancement != Some(Stone))
+        .min_by_key(|c| c.rank);
     // Unplayed cards in hand
-    for card in &hand {
-        if card.enhancement == Some(Steel) {
-            mult *= 1.5
+    for card in hand {
+        if state.is_debuffed(card) {
+            continue;
         }
-        for joker in play.jokers() {
-            match joker.kind {
-                Baron => {
-                    if card.rank == King {
-                        mult *= 1.5
+        for trigger in 0..=held_retriggers(&kinds, card) {
+            let name = || format!("Held {}", card_name(card, trigger));
+            let before = (chips, mult, money + round_money);
+            for effect in card.enhancement.map_or(&[][..], effects::held_enhancement) {
+                apply_effect(effect, &mut chips, &mut mult, &mut money, &mut round_money);
+            }
+            tracer.record(name, before, (chips, mult, money + round_money));
+            for kind in &kinds {
+                let before = (chips, mult, money + round_money);
+                match **kind {
+                    Baron => {
+                        if card.rank == King {
+                            mult *= 1.5
+                        }
                     }
-                }
-                ReservedParking { .. } => match card.rank {
-                    Jack | Queen | King => todo!(),
-                    _ => {}
-                },
-                RaisedFist => {
-                    if card.rank == hand.iter().min_by_key(|c| c.rank).unwrap().rank {
-                        mult += get_chips_from_rank(card.rank);
+                    ReservedParking { .. } => {
+                        if is_face(card, pareidolia) && rolls.roll(odds(2.0)) {
+                            money += 1.0
+                        }
+                    }
+                    RaisedFist => {
+                        if lowest_held.is_some_and(|c| ptr::eq(*c, *card)) {
+                            mult += 2.0 * get_chips_from_rank(card.rank);
+                        }
                     }
+                    ShootTheMoon => {
+                        if card.rank == Queen {
+                            mult += 13.0
+                        }
+                    }
+                    _ => {}
                 }
-                _ => {}
+                tracer.record(
+                    || format!("{kind:?} on {}", name()),
+                    before,
+                    (chips, mult, money + round_money),
+                );
             }
         }
     }
-    // Jokers that trigger by themselves
-    for joker in play.jokers() {
-        match joker.kind {
-            Joker => mult += 4.0,
-            Jolly => {
-                if play.poker_hand().unwrap().kind == Pair {
-                    mult += 8.0
-                }
+    // Jokers that trigger by themselves, left to right. Foil and Holographic
+    // add before the joker's own effect, Polychrome multiplies after it.
+    for (joker, kind) in state.jokers.iter().zip(&copies) {
+        if let Some(edition) = joker.edition {
+            let before = (chips, mult, money + round_money);
+            match edition {
+                JokerEdition::Foil => chips += 50.0,
+                JokerEdition::Holographic => mult += 10.0,
+                _ => {}
             }
-            Zany => {
-                if play.poker_hand().unwrap().kind == ThreeOfAKind {
-                    mult += 12.0
+            tracer.record(
+                || format!("{edition:?} {:?}", joker.kind),
+                before,
+                (chips, mult, money + round_money),
+            );
+        }
+        if let Some(kind) = kind {
+            let before = (chips, mult, money + round_money);
+            match **kind {
+                Joker => mult += 4.0,
+                Jolly => {
+                    if hand_kind == Pair {
+                        mult += 8.0
+                    }
                 }
-            }
-            Mad => {
-                if play.poker_hand().unwrap().kind == TwoPair {
-                    mult += 10.0
+                Zany => {
+                    if hand_kind == ThreeOfAKind {
+                        mult += 12.0
+                    }
                 }
-            }
-            Crazy => {
-                if play.poker_hand().unwrap().kind == Straight {
-                    mult += 12.0
+                Mad => {
+                    if hand_kind == TwoPair {
+                        mult += 10.0
+                    }
                 }
-            }
-            Droll => {
-                if play.poker_hand().unwrap().kind == Flush {
-                    mult += 10.0
+                Crazy => {
+                    if hand_kind == Straight {
+                        mult += 12.0
+                    }
                 }
-            }
-            Sly => {
-                if play.poker_hand().unwrap().kind == Pair {
-                    chips += 50.0
+                Droll => {
+                    if hand_kind == Flush {
+                        mult += 10.0
+                    }
                 }
-            }
-            Wily => {
-                if play.poker_hand().unwrap().kind == ThreeOfAKind {
-                    chips += 100.0
+                Sly => {
+                    if hand_kind == Pair {
+                        chips += 50.0
+                    }
                 }
-            }
-            Clever => {
-                if play.poker_hand().unwrap().kind == TwoPair {
-                    chips += 80.0
+                Wily => {
+                    if hand_kind == ThreeOfAKind {
+                        chips += 100.0
+                    }
                 }
-            }
-            Devious => {
-                if play.poker_hand().unwrap().kind == Straight {
-                    chips += 100.0
+                Clever => {
+                    if hand_kind == TwoPair {
+                        chips += 80.0
+                    }
                 }
-            }
-            Crafty => {
-                if play.poker_hand().unwrap().kind == Flush {
-                    chips += 80.0
+                Devious => {
+                    if hand_kind == Straight {
+                        chips += 100.0
+                    }
                 }
-            }
-            Half => {
-                if play.hand().iter().filter(|c| c.selected).count() <= 3 {
-                    mult += 20.0
+                Crafty => {
+                    if hand_kind == Flush {
+                        chips += 80.0
+                    }
                 }
-            }
-            Stencil { xmult } => mult *= xmult as f64,
-            Banner => chips += 30.0 * f64::from(play.discards()),
-            MysticSummit => {
-                if play.discards() == 0 {
-                    mult += 15.0
+                Half => {
+                    if played_count <= 3 {
+                        mult += 20.0
+                    }
                 }
-            }
-            LoyaltyCard { left } => {
-                if left == 0 {
-                    mult *= 4.0
+                Stencil { xmult } => mult *= xmult as f64,
+                Banner => chips += 30.0 * f64::from(state.discards),
+                MysticSummit => {
+                    if state.discards == 0 {
+                        mult += 15.0
+                    }
                 }
-            }
-            Misprint => mult += 23.0,
-            GrosMichel { .. } => mult += 15.0,
-            Cavendish { .. } => mult *= 3.0,
-            Supernova => mult += get_supernova_mult(play),
-            Blackboard => {
-                if play.hand().iter().filter(|c| !c.selected).all(|c| {
-                    c.card.as_ref().unwrap().suit == Spades
-                        || c.card.as_ref().unwrap().suit == Clubs
-                }) {
-                    mult *= 3.0
+                LoyaltyCard { left } => {
+                    if left == 0 {
+                        mult *= 4.0
+                    }
                 }
-            }
-            TodoList { poker_hand } => {
-                if poker_hand == play.poker_hand().unwrap().kind {
-                    todo!("Implement money gain")
+                Misprint => mult += rolls.choose(vec![1.0 / 24.0; 24]) as f64,
+                GrosMichel { .. } => mult += 15.0,
+                Cavendish { .. } => mult *= 3.0,
+                Supernova => mult += get_supernova_mult(state, hand_kind),
+                Blackboard => {
+                    if hand.iter().all(|c| {
+                        let debuffed = state.is_debuffed(c);
+                        is_suit(c, Spades, smeared, debuffed)
+                            || is_suit(c, Clubs, smeared, debuffed)
+                    }) {
+                        mult *= 3.0
+                    }
                 }
-            }
-            CardSharp => {
-                if get_card_sharp_state(play) {
-                    mult *= 3.0
+                CardSharp => {
+                    if get_card_sharp_state(state, hand_kind) {
+                        mult *= 3.0
+                    }
                 }
-            }
-            Vampire { xmult } => mult *= xmult + 0.1 * scored.iter().filter(|c| c.enhancement.is_some()).count() as f64,
-            Bull => chips += 2.0 * f64::from(play.money()),
-            Acrobat => {
-                if play.hands() == 1 {
-                    mult *= 3.0;
+                Vampire { xmult } => mult *= xmult + 0.1 * vampire_meals as f64,
+                Obelisk { xmult } => mult *= get_obelisk_xmult(state, hand_kind, xmult),
+                Bull => chips += 2.0 * (state.money as f64 + money),
+                Acrobat => {
+                    if state.hands == 1 {
+                        mult *= 3.0;
+                    }
                 }
-            }
-            FlowerPot => {
-                if [Spades, Clubs, Diamonds, Hearts].iter().all(|suit| {
-                    play.hand()
-                        .iter()
-                        .filter(|c| c.selected)
-                        .any(|c| c.card.as_ref().unwrap().suit == *suit)
-                }) {
-                    mult *= 3.0
+                FlowerPot => {
+                    if covers_suits(
+                        &scored,
+                        &[&[Spades], &[Clubs], &[Diamonds], &[Hearts]],
+                        smeared,
+                        &state.debuffed,
+                    ) {
+                        mult *= 3.0
+                    }
                 }
-            }
-            Blueprint => todo!(),
-            SeeingDouble => {
-                if play
-                    .hand()
-                    .iter()
-                    .filter(|c| c.selected)
-                    .any(|c| c.card.as_ref().unwrap().suit == Clubs)
-                    && [Spades, Diamonds, Hearts].iter().any(|suit| {
-                        play.hand()
-                            .iter()
-                            .filter(|c| c.selected)
-                            .any(|c| c.card.as_ref().unwrap().suit == *suit)
-                    })
-                {
-                    mult *= 3.0;
+                SeeingDouble => {
+                    if covers_suits(
+                        &scored,
+                        &[&[Clubs], &[Spades, Diamonds, Hearts]],
+                        smeared,
+                        &state.debuffed,
+                    ) {
+                        mult *= 3.0;
+                    }
                 }
-            }
-            Matador => todo!("Add money handling"),
-            Duo => {
-                if play.poker_hand().unwrap().kind == Pair {
-                    mult *= 2.0
+                TodoList { poker_hand } => {
+                    if poker_hand == hand_kind {
+                        money += 4.0
+                    }
                 }
-            }
-            Trio => {
-                if play.poker_hand().unwrap().kind == ThreeOfAKind {
-                    mult *= 3.0
+                Matador => {
+                    if boss_triggered {
+                        money += 8.0
+                    }
                 }
-            }
-            Family => {
-                if play.poker_hand().unwrap().kind == FourOfAKind {
-                    mult *= 4.0
+                Duo => {
+                    if hand_kind == Pair {
+                        mult *= 2.0
+                    }
                 }
-            }
-            Order => {
-                if play.poker_hand().unwrap().kind == Straight {
-                    mult *= 3.0
+                Trio => {
+                    if hand_kind == ThreeOfAKind {
+                        mult *= 3.0
+                    }
                 }
-            }
-            Tribe => {
-                if play.poker_hand().unwrap().kind == Flush {
-                    mult *= 2.0
+                Family => {
+                    if hand_kind == FourOfAKind {
+                        mult *= 4.0
+                    }
                 }
-            }
-            Stuntman => chips += 250.0,
-            DriversLicense { cards } => {
-                if cards >= 16 {
-                    mult *= 3.0
+                Order => {
+                    if hand_kind == Straight {
+                        mult *= 3.0
+                    }
                 }
+                Tribe => {
+                    if hand_kind == Flush {
+                        mult *= 2.0
+                    }
+                }
+                Stuntman => chips += 250.0,
+                // Grows before it triggers, unless a face card scores
+                RideTheBus { mult: jmult } => {
+                    if !scored.iter().any(|&c| is_scored_face(c)) {
+                        mult += jmult as f64 + 1.0
+                    }
+                }
+                DriversLicense { cards } => {
+                    if cards >= 16 {
+                        mult *= 3.0
+                    }
+                }
+                // +chips jokers
+                Runner { chips: jchips }
+                | IceCream { chips: jchips }
+                | BlueJoker { chips: jchips }
+                | Square { chips: jchips }
+                | JokerKind::Stone { chips: jchips }
+                | Castle {
+                    chips: jchips,
+                    suit: _,
+                }
+                | Wee { chips: jchips } => chips += jchips as f64,
+                // +mult Jokers:
+                Ceremonial { mult: jmult }
+                | Abstract { mult: jmult }
+                | GreenJoker { mult: jmult }
+                | RedCard { mult: jmult }
+                | Erosion { mult: jmult }
+                | FortuneTeller { mult: jmult }
+                | Flash { mult: jmult }
+                | Popcorn { mult: jmult }
+                | Trousers { mult: jmult }
+                | Swashbuckler { mult: jmult }
+                | Bootstraps { mult: jmult } => mult += jmult as f64,
+                // xmult jokers:
+                SteelJoker { xmult }
+                | Constellation { xmult }
+                | Madness { xmult }
+                | Hologram { xmult }
+                | Ramen { xmult }
+                | Campfire { xmult }
+                | Throwback { xmult }
+                | JokerKind::Glass { xmult }
+                | HitTheRoad { xmult }
+                | Caino { xmult }
+                | Yorick { xmult } => mult *= xmult,
+                _ => {}
             }
-            // +chips jokers
-            Runner { chips: jchips } |
-            IceCream { chips: jchips } |
-            BlueJoker { chips: jchips } |
-            Square { chips: jchips } |
-            JokerKind::Stone { chips: jchips } |
-            Castle { chips: jchips, suit: _, } |
-            Wee { chips: jchips } => chips += jchips as f64,
-            // +mult Jokers:
-            Ceremonial { mult: jmult } |
-            Abstract { mult: jmult } |
-            GreenJoker { mult: jmult } |
-            RedCard { mult: jmult } |
-            Erosion { mult: jmult } |
-            FortuneTeller { mult: jmult } |
-            Flash { mult: jmult } |
-            Popcorn { mult: jmult } |
-            Trousers { mult: jmult } |
-            Swashbuckler { mult: jmult } |
-            Bootstraps { mult: jmult } => mult += jmult as f64,
-            // xmult jokers:
-            SteelJoker { xmult } |
-            Constellation { xmult } |
-            Madness { xmult } |
-            Hologram { xmult } |
-            Obelisk { xmult } |
-            Ramen { xmult } |
-            Campfire { xmult } |
-            Throwback { xmult } |
-            JokerKind::Glass { xmult } |
-            HitTheRoad { xmult } |
-            Caino { xmult } |
-            Yorick { xmult } => mult *= xmult,
-            _ => {}
-        }
-        if let Some(e) = joker.edition {
-            match e {
-                JokerEdition::Foil => chips += 50.0,
-                JokerEdition::Holographic => mult += 10.0,
-                JokerEdition::Polychrome => mult *= 1.5,
-                JokerEdition::Negative => {}
-            }
+            tracer.record(
+                || format!("{kind:?}"),
+                before,
+                (chips, mult, money + round_money),
+            );
         }
+        if joker.edition == Some(JokerEdition::Polychrome) {
+            let before = (chips, mult, money + round_money);
+            mult *= 1.5;
+            tracer.record(
+                || format!("Polychrome {:?}", joker.kind),
+                before,
+                (chips, mult, money + round_money),
+            );
+        }
+    }
+    if state.rules.balance_chips_and_mult {
+        let before = (chips, mult, money + round_money);
+        mult = chips.midpoint(mult).floor();
+        chips = mult;
+        tracer.record(
+            || "Plasma Deck".to_string(),
+            before,
+            (chips, mult, money + round_money),
+        );
+    }
+    Score {
+        chips,
+        mult,
+        money,
+        round_money,
+        shattered,
     }
-    if play.run_info().deck == Plasma {
-        mult = f64::midpoint(chips, mult).floor();
-        chips = mult
+}
+
+/// Whether the boss blind debuffs `card`.
+fn boss_debuffs(boss: BossBlind, card: &PlayingCard, smeared: bool, pareidolia: bool) -> bool {
+    match boss {
+        // Wild cards are debuffed along with every suit
+        BossBlind::TheClub => is_suit(card, Clubs, smeared, false),
+        BossBlind::TheGoad => is_suit(card, Spades, smeared, false),
+        BossBlind::TheWindow => is_suit(card, Diamonds, smeared, false),
+        BossBlind::TheHead => is_suit(card, Hearts, smeared, false),
+        BossBlind::ThePlant => is_face(card, pareidolia),
+        // Until a joker is sold, which the bot never does
+        BossBlind::VerdantLeaf => true,
+        // The Pillar needs to know which cards were played earlier this ante,
+        // which the game doesn't tell us
+        _ => false,
     }
-    println!("{:?} {chips} {mult}", play.poker_hand().unwrap().kind);
-    chips * mult
+}
+
+/// Whether the boss blind lets `kind` score at all.
+fn boss_allows(state: &GameState, kind: PokerHandKind) -> bool {
+    match state.boss {
+        Some(BossBlind::ThePsychic) => state.selected.len() == 5,
+        Some(BossBlind::TheEye) => state.hand_levels.played_round(kind) == 0,
+        Some(BossBlind::TheMouth) => state
+            .hand_levels
+            .iter()
+            .all(|h| h.kind == kind || h.played_round == 0),
+        _ => true,
+    }
+}
+
+/// Whether the engine knows how `kind` changes the score. Jokers that don't
+/// touch chips or mult at all count as supported, and any joker not listed
+/// here, such as Baseball Card, doesn't.
+pub fn is_supported(kind: &JokerKind) -> bool {
+    matches!(
+        kind,
+        // Scored by the engine
+        Joker
+            | GreedyJoker
+            | LustyJoker
+            | WrathfulJoker
+            | GluttenousJoker
+            | Jolly
+            | Zany
+            | Mad
+            | Crazy
+            | Droll
+            | Sly
+            | Wily
+            | Clever
+            | Devious
+            | Crafty
+            | Half
+            | Stencil { .. }
+            | FourFingers
+            | Mime
+            | Ceremonial { .. }
+            | Banner
+            | MysticSummit
+            | LoyaltyCard { .. }
+            | EightBall { .. }
+            | Misprint
+            | Dusk
+            | RaisedFist
+            | Fibonacci
+            | SteelJoker { .. }
+            | ScaryFace
+            | Abstract { .. }
+            | Hack
+            | Pareidolia
+            | GrosMichel { .. }
+            | EvenSteven
+            | OddTodd
+            | Scholar
+            | Business { .. }
+            | Supernova
+            | RideTheBus { .. }
+            | SpaceJoker
+            | Blackboard
+            | Runner { .. }
+            | IceCream { .. }
+            | Splash
+            | BlueJoker { .. }
+            | Constellation { .. }
+            | GreenJoker { .. }
+            | TodoList { .. }
+            | Cavendish { .. }
+            | CardSharp
+            | RedCard { .. }
+            | Madness { .. }
+            | Square { .. }
+            | Vampire { .. }
+            | Shortcut
+            | Hologram { .. }
+            | Baron
+            | Obelisk { .. }
+            | MidasMask { .. }
+            | Photograph
+            | Erosion { .. }
+            | ReservedParking { .. }
+            | FortuneTeller { .. }
+            | JokerKind::Stone { .. }
+            | Bull
+            | Flash { .. }
+            | Popcorn { .. }
+            | Trousers { .. }
+            | Ancient { .. }
+            | Ramen { .. }
+            | WalkieTalkie
+            | Seltzer { .. }
+            | Castle { .. }
+            | Smiley
+            | Campfire { .. }
+            | Ticket
+            | Acrobat
+            | SockAndBuskin
+            | Swashbuckler { .. }
+            | Smeared
+            | Throwback { .. }
+            | HangingChad
+            | RoughGem
+            | Bloodstone { .. }
+            | Arrowhead
+            | OnyxAgate
+            | JokerKind::Glass { .. }
+            | FlowerPot
+            | Blueprint
+            | Wee { .. }
+            | Oops
+            | Idol { .. }
+            | SeeingDouble
+            | Matador
+            | HitTheRoad { .. }
+            | Duo
+            | Trio
+            | Family
+            | Order
+            | Tribe
+            | Stuntman
+            | Brainstorm
+            | ShootTheMoon
+            | DriversLicense { .. }
+            | Bootstraps { .. }
+            | Caino { .. }
+            | Triboulet
+            | Yorick { .. }
+            | Chicot { .. }
+            // Never change chips or mult
+            | CreditCard { .. }
+            | Marble { .. }
+            | Chaos { .. }
+            | DelayedGrat { .. }
+            | Egg { .. }
+            | Burglar { .. }
+            | SixthSense { .. }
+            | Faceless { .. }
+            | Superposition { .. }
+            | Seance { .. }
+            | RiffRaff { .. }
+            | Vagabond { .. }
+            | CloudNine { .. }
+            | Rocket { .. }
+            | Luchador { .. }
+            | Gift { .. }
+            | TurtleBean { .. }
+            | Mail { .. }
+            | ToTheMoon { .. }
+            | Hallucination { .. }
+            | Juggler { .. }
+            | Drunkard { .. }
+            | Golden { .. }
+            | DietCola { .. }
+            | TradingCard { .. }
+            | MrBones { .. }
+            | Troubadour { .. }
+            | Certificate { .. }
+            | Showman { .. }
+            | MerryAndy { .. }
+            | Invisible { .. }
+            | Satellite { .. }
+            | Cartomancer { .. }
+            | Astronomer { .. }
+            | Burnt { .. }
+            | Perkeo { .. }
+    )
+}
+
+fn apply_effect(
+    effect: &Effect,
+    chips: &mut BigNum,
+    mult: &mut BigNum,
+    money: &mut f64,
+    round_money: &mut f64,
+) {
+    *chips += effect.chips;
+    *mult += effect.mult;
+    *money += effect.money;
+    *round_money += effect.round_money;
+    *mult *= effect.xmult;
+}
+
+/// Names a card in a trace, noting which retrigger it's on.
+fn card_name(card: &PlayingCard, trigger: usize) -> String {
+    if trigger == 0 {
+        format!("{:?} of {:?}", card.rank, card.suit)
+    } else {
+        format!("{:?} of {:?} (retrigger {trigger})", card.rank, card.suit)
+    }
+}
+
+/// How many extra times a scored card triggers.
+fn played_retriggers(
+    state: &GameState,
+    kinds: &[&JokerKind],
+    card: &PlayingCard,
+    first: bool,
+    pareidolia: bool,
+) -> usize {
+    let mut retriggers = usize::from(matches!(card.seal, Some(Red)));
+    for kind in kinds {
+        retriggers += match **kind {
+            Hack => usize::from(matches!(card.rank, Two | Three | Four | Five)),
+            Dusk => usize::from(state.hands == 1),
+            SockAndBuskin => usize::from(is_face(card, pareidolia)),
+            Seltzer { .. } => 1,
+            HangingChad if first => 2,
+            _ => 0,
+        };
+    }
+    retriggers
+}
+
+/// How many extra times a card held in hand triggers.
+fn held_retriggers(kinds: &[&JokerKind], card: &PlayingCard) -> usize {
+    usize::from(matches!(card.seal, Some(Red))) + kinds.iter().filter(|k| ***k == Mime).count()
+}
+
+/// The ability each joker uses, following Blueprint (the joker to its right)
+/// and Brainstorm (the leftmost joker) to whatever they end up copying.
+/// `None` when a copier has nothing it can copy.
+fn resolve_jokers<'a>(jokers: &[&'a jokers::Joker]) -> Vec<Option<&'a JokerKind>> {
+    (0..jokers.len())
+        .map(|i| {
+            let mut target = i;
+            // A chain can visit each joker once before it must be a loop
+            for _ in 0..jokers.len() {
+                let kind = &jokers[target].kind;
+                target = match kind {
+                    Blueprint => target + 1,
+                    Brainstorm => 0,
+                    _ if target == i => return Some(kind),
+                    _ => return is_copyable(kind).then_some(kind),
+                };
+                if target >= jokers.len() {
+                    return None;
+                }
+            }
+            None
+        })
+        .collect()
+}
+
+/// Whether Blueprint and Brainstorm can copy `kind`. Jokers that only change
+/// the rules of the game have no ability to copy.
+fn is_copyable(kind: &JokerKind) -> bool {
+    !matches!(kind, FourFingers | Shortcut | Smeared | Splash | Pareidolia)
+}
+
+/// Whether `card` counts as a face card. Pareidolia makes every card one.
+fn is_face(card: &PlayingCard, pareidolia: bool) -> bool {
+    pareidolia || matches!(card.rank, Jack | Queen | King)
 }
 
 fn get_chips_from_rank(rank: Rank) -> f64 {
@@ -536,22 +1467,859 @@ fn get_chips_from_rank(rank: Rank) -> f64 {
     }
 }
 
-fn get_supernova_mult(play: &Play) -> f64 {
-    match play.poker_hand() {
-        Some(hand) => {
-            let hands = &play.run_info().poker_hands.clone().into_iter();
-            hands.clone().find(|h| h.hand == *hand).unwrap().played as f64
-        }
-        None => 0.0,
+/// Supernova counts the hand being played too.
+fn get_supernova_mult(state: &GameState, kind: PokerHandKind) -> f64 {
+    f64::from(state.hand_levels.played(kind) + 1)
+}
+
+fn get_card_sharp_state(state: &GameState, kind: PokerHandKind) -> bool {
+    state.hand_levels.played_round(kind) >= 1
+}
+
+/// Obelisk grows before it triggers, unless the hand is still the most played
+/// one, which resets it.
+fn get_obelisk_xmult(state: &GameState, kind: PokerHandKind, xmult: f64) -> f64 {
+    if state.hand_levels.is_most_played_after(kind) {
+        1.0
+    } else {
+        xmult + 0.2
     }
 }
 
-fn get_card_sharp_state(play: &Play) -> bool {
-    match play.poker_hand() {
-        Some(hand) => {
-            let hands = &play.run_info().poker_hands.clone().into_iter();
-            hands.clone().find(|h| h.hand == *hand).unwrap().played_round >= 1
+#[cfg(test)]
+mod tests {
+    use remotro::balatro::{
+        deck::{
+            CardEdition,
+            Enhancement::{self, *},
+            PlayingCard,
+            Rank::{self, *},
+            Seal,
+            Suit::{self, *},
+        },
+        menu::Deck,
+    };
+
+    use super::*;
+
+    fn card(rank: Rank, suit: Suit) -> PlayingCard {
+        PlayingCard {
+            rank,
+            suit,
+            enhancement: None,
+            edition: None,
+            seal: None,
+        }
+    }
+
+    fn enhanced(rank: Rank, suit: Suit, enhancement: Enhancement) -> PlayingCard {
+        PlayingCard {
+            enhancement: Some(enhancement),
+            ..card(rank, suit)
+        }
+    }
+
+    /// Whether `scored` is exactly `expected`, by identity and in order.
+    fn same_cards(scored: &[&PlayingCard], expected: &[&PlayingCard]) -> bool {
+        scored.len() == expected.len() && scored.iter().zip(expected).all(|(a, b)| ptr::eq(*a, *b))
+    }
+
+    #[test]
+    fn stone_scores_once_beside_high_card() {
+        let stone = enhanced(Ace, Spades, Stone);
+        let seven = card(Seven, Hearts);
+        let (kind, scored) = evaluate_hand(&[&stone, &seven], HandModifiers::default(), &[]);
+        assert_eq!(kind, HighCard);
+        assert!(same_cards(&scored, &[&stone, &seven]));
+    }
+
+    #[test]
+    fn stone_is_not_part_of_a_pair() {
+        let stone = enhanced(Seven, Spades, Stone);
+        let seven = card(Seven, Hearts);
+        let other_seven = card(Seven, Clubs);
+        let (kind, scored) = evaluate_hand(
+            &[&stone, &seven, &other_seven],
+            HandModifiers::default(),
+            &[],
+        );
+        assert_eq!(kind, Pair);
+        assert!(same_cards(&scored, &[&stone, &seven, &other_seven]));
+    }
+
+    #[test]
+    fn stone_is_not_part_of_a_three_of_a_kind() {
+        let stone = enhanced(King, Spades, Stone);
+        let kings = [card(King, Hearts), card(King, Clubs), card(King, Diamonds)];
+        let two = card(Two, Hearts);
+        let played = [&kings[0], &stone, &kings[1], &two, &kings[2]];
+        let (kind, scored) = evaluate_hand(&played, HandModifiers::default(), &[]);
+        assert_eq!(kind, ThreeOfAKind);
+        assert!(same_cards(
+            &scored,
+            &[&kings[0], &stone, &kings[1], &kings[2]]
+        ));
+    }
+
+    #[test]
+    fn stone_is_not_part_of_two_pair() {
+        let stone = enhanced(Four, Spades, Stone);
+        let fours = [card(Four, Hearts), card(Four, Clubs)];
+        let nines = [card(Nine, Hearts), card(Nine, Clubs)];
+        let played = [&stone, &fours[0], &nines[0], &fours[1], &nines[1]];
+        let (kind, scored) = evaluate_hand(&played, HandModifiers::default(), &[]);
+        assert_eq!(kind, TwoPair);
+        assert!(same_cards(&scored, &played));
+    }
+
+    const FOUR_FINGERS: HandModifiers = HandModifiers {
+        four_fingers: true,
+        shortcut: false,
+        smeared: false,
+        splash: false,
+    };
+    const SHORTCUT: HandModifiers = HandModifiers {
+        four_fingers: false,
+        shortcut: true,
+        smeared: false,
+        splash: false,
+    };
+    const SMEARED: HandModifiers = HandModifiers {
+        four_fingers: false,
+        shortcut: false,
+        smeared: true,
+        splash: false,
+    };
+
+    #[test]
+    fn four_fingers_flush_scores_the_four_suited_cards() {
+        let hearts = [
+            card(Two, Hearts),
+            card(Six, Hearts),
+            card(Nine, Hearts),
+            card(King, Hearts),
+        ];
+        let spade = card(Four, Spades);
+        let played = [&hearts[0], &spade, &hearts[1], &hearts[2], &hearts[3]];
+        assert_eq!(
+            classify_hand(&played, HandModifiers::default(), &[]),
+            HighCard
+        );
+        let (kind, scored) = evaluate_hand(&played, FOUR_FINGERS, &[]);
+        assert_eq!(kind, Flush);
+        assert!(same_cards(
+            &scored,
+            &[&hearts[0], &hearts[1], &hearts[2], &hearts[3]]
+        ));
+    }
+
+    #[test]
+    fn four_fingers_straight_scores_the_four_card_run() {
+        let run = [
+            card(Five, Hearts),
+            card(Six, Spades),
+            card(Seven, Clubs),
+            card(Eight, Diamonds),
+        ];
+        let king = card(King, Hearts);
+        let played = [&run[0], &run[1], &king, &run[2], &run[3]];
+        let (kind, scored) = evaluate_hand(&played, FOUR_FINGERS, &[]);
+        assert_eq!(kind, Straight);
+        assert!(same_cards(&scored, &[&run[0], &run[1], &run[2], &run[3]]));
+    }
+
+    #[test]
+    fn shortcut_straight_allows_gaps() {
+        let cards = [
+            card(Two, Hearts),
+            card(Four, Spades),
+            card(Six, Clubs),
+            card(Eight, Diamonds),
+            card(Ten, Hearts),
+        ];
+        let played: Vec<&PlayingCard> = cards.iter().collect();
+        assert_eq!(
+            classify_hand(&played, HandModifiers::default(), &[]),
+            HighCard
+        );
+        let (kind, scored) = evaluate_hand(&played, SHORTCUT, &[]);
+        assert_eq!(kind, Straight);
+        assert!(same_cards(&scored, &played));
+    }
+
+    #[test]
+    fn shortcut_with_four_fingers_needs_four_cards() {
+        let cards = [
+            card(Three, Hearts),
+            card(Five, Spades),
+            card(Seven, Clubs),
+            card(Nine, Diamonds),
+            card(King, Hearts),
+        ];
+        let played: Vec<&PlayingCard> = cards.iter().collect();
+        let both = HandModifiers {
+            shortcut: true,
+            ..FOUR_FINGERS
+        };
+        assert_eq!(classify_hand(&played, SHORTCUT, &[]), HighCard);
+        assert_eq!(classify_hand(&played, FOUR_FINGERS, &[]), HighCard);
+        let (kind, scored) = evaluate_hand(&played, both, &[]);
+        assert_eq!(kind, Straight);
+        assert!(same_cards(&scored, &played[..4]));
+    }
+
+    #[test]
+    fn smeared_flush_mixes_hearts_and_diamonds() {
+        let cards = [
+            card(Two, Hearts),
+            card(Five, Diamonds),
+            card(Eight, Hearts),
+            card(Jack, Diamonds),
+            card(King, Hearts),
+        ];
+        let played: Vec<&PlayingCard> = cards.iter().collect();
+        assert_eq!(
+            classify_hand(&played, HandModifiers::default(), &[]),
+            HighCard
+        );
+        let (kind, scored) = evaluate_hand(&played, SMEARED, &[]);
+        assert_eq!(kind, Flush);
+        assert!(same_cards(&scored, &played));
+    }
+
+    #[test]
+    fn smeared_does_not_mix_colours() {
+        let cards = [
+            card(Two, Hearts),
+            card(Five, Spades),
+            card(Eight, Hearts),
+            card(Jack, Diamonds),
+            card(King, Hearts),
+        ];
+        let played: Vec<&PlayingCard> = cards.iter().collect();
+        assert_eq!(classify_hand(&played, SMEARED, &[]), HighCard);
+    }
+
+    #[test]
+    fn four_card_straight_flush_scores_the_union() {
+        let five = card(Five, Hearts);
+        let six = card(Six, Hearts);
+        let seven = card(Seven, Hearts);
+        let nine = card(Nine, Hearts);
+        let eight = card(Eight, Spades);
+        let played = [&five, &six, &seven, &nine, &eight];
+        let (kind, scored) = evaluate_hand(&played, FOUR_FINGERS, &[]);
+        assert_eq!(kind, StraightFlush);
+        // Nine is only in the flush and the Eight only in the straight
+        assert!(same_cards(&scored, &played));
+    }
+
+    #[test]
+    fn shortcut_straight_flush_allows_gaps() {
+        let cards = [
+            card(Two, Spades),
+            card(Four, Spades),
+            card(Six, Spades),
+            card(Eight, Spades),
+            card(Ten, Spades),
+        ];
+        let played: Vec<&PlayingCard> = cards.iter().collect();
+        assert_eq!(classify_hand(&played, HandModifiers::default(), &[]), Flush);
+        let (kind, scored) = evaluate_hand(&played, SHORTCUT, &[]);
+        assert_eq!(kind, StraightFlush);
+        assert!(same_cards(&scored, &played));
+    }
+
+    #[test]
+    fn shortcut_four_fingers_straight_flush_scores_the_union() {
+        let clubs = [
+            card(Three, Clubs),
+            card(Five, Clubs),
+            card(Seven, Clubs),
+            card(Queen, Clubs),
+        ];
+        let nine = card(Nine, Diamonds);
+        let played = [&clubs[0], &clubs[1], &clubs[2], &nine, &clubs[3]];
+        let both = HandModifiers {
+            shortcut: true,
+            ..FOUR_FINGERS
+        };
+        assert_eq!(classify_hand(&played, FOUR_FINGERS, &[]), Flush);
+        let (kind, scored) = evaluate_hand(&played, both, &[]);
+        assert_eq!(kind, StraightFlush);
+        assert!(same_cards(&scored, &played));
+    }
+
+    #[test]
+    fn smeared_straight_flush_merges_suits() {
+        let cards = [
+            card(Five, Hearts),
+            card(Six, Diamonds),
+            card(Seven, Hearts),
+            card(Eight, Diamonds),
+            card(Nine, Hearts),
+        ];
+        let played: Vec<&PlayingCard> = cards.iter().collect();
+        assert_eq!(
+            classify_hand(&played, HandModifiers::default(), &[]),
+            Straight
+        );
+        let (kind, scored) = evaluate_hand(&played, SMEARED, &[]);
+        assert_eq!(kind, StraightFlush);
+        assert!(same_cards(&scored, &played));
+    }
+
+    #[test]
+    fn smeared_straight_flush_needs_one_colour() {
+        let cards = [
+            card(Five, Hearts),
+            card(Six, Diamonds),
+            card(Seven, Clubs),
+            card(Eight, Diamonds),
+            card(Nine, Hearts),
+        ];
+        let played: Vec<&PlayingCard> = cards.iter().collect();
+        assert_eq!(classify_hand(&played, SMEARED, &[]), Straight);
+    }
+
+    #[test]
+    fn flush_with_a_stone_card_scores_the_stone() {
+        let hearts = [
+            card(Two, Hearts),
+            card(Six, Hearts),
+            card(Nine, Hearts),
+            card(King, Hearts),
+        ];
+        let stone = enhanced(Ace, Hearts, Stone);
+        let played = [&hearts[0], &hearts[1], &stone, &hearts[2], &hearts[3]];
+        assert_eq!(
+            classify_hand(&played, HandModifiers::default(), &[]),
+            HighCard
+        );
+        let (kind, scored) = evaluate_hand(&played, FOUR_FINGERS, &[]);
+        assert_eq!(kind, Flush);
+        assert!(same_cards(&scored, &played));
+    }
+
+    #[test]
+    fn wild_card_completes_a_flush() {
+        let hearts = [
+            card(Two, Hearts),
+            card(Six, Hearts),
+            card(Nine, Hearts),
+            card(King, Hearts),
+        ];
+        let wild = enhanced(Four, Spades, Wild);
+        let played = [&hearts[0], &wild, &hearts[1], &hearts[2], &hearts[3]];
+        let (kind, scored) = evaluate_hand(&played, HandModifiers::default(), &[]);
+        assert_eq!(kind, Flush);
+        assert!(same_cards(&scored, &played));
+    }
+
+    #[test]
+    fn debuffed_wild_card_keeps_its_own_suit() {
+        let hearts = [
+            card(Two, Hearts),
+            card(Six, Hearts),
+            card(Nine, Hearts),
+            card(King, Hearts),
+        ];
+        let wild = enhanced(Four, Spades, Wild);
+        let played = [&hearts[0], &wild, &hearts[1], &hearts[2], &hearts[3]];
+        assert_eq!(
+            classify_hand(&played, HandModifiers::default(), &[&wild]),
+            HighCard
+        );
+    }
+
+    #[test]
+    fn wild_cards_with_smeared_and_four_fingers() {
+        let cards = [
+            card(Two, Hearts),
+            card(Six, Diamonds),
+            enhanced(Nine, Clubs, Wild),
+            card(King, Hearts),
+            card(Queen, Spades),
+        ];
+        let played: Vec<&PlayingCard> = cards.iter().collect();
+        let both = HandModifiers {
+            smeared: true,
+            ..FOUR_FINGERS
+        };
+        assert_eq!(classify_hand(&played, FOUR_FINGERS, &[]), HighCard);
+        let (kind, scored) = evaluate_hand(&played, both, &[]);
+        assert_eq!(kind, Flush);
+        assert!(same_cards(&scored, &played[..4]));
+    }
+
+    #[test]
+    fn wild_card_fills_a_straight_flush() {
+        let hearts = [
+            card(Five, Hearts),
+            card(Six, Hearts),
+            card(Eight, Hearts),
+            card(Nine, Hearts),
+        ];
+        let wild = enhanced(Seven, Clubs, Wild);
+        let played = [&hearts[0], &hearts[1], &wild, &hearts[2], &hearts[3]];
+        let (kind, scored) = evaluate_hand(&played, HandModifiers::default(), &[]);
+        assert_eq!(kind, StraightFlush);
+        assert!(same_cards(&scored, &played));
+    }
+
+    #[test]
+    fn debuffed_wild_card_breaks_a_straight_flush() {
+        let hearts = [
+            card(Five, Hearts),
+            card(Six, Hearts),
+            card(Eight, Hearts),
+            card(Nine, Hearts),
+        ];
+        let wild = enhanced(Seven, Clubs, Wild);
+        let played = [&hearts[0], &hearts[1], &wild, &hearts[2], &hearts[3]];
+        let (kind, scored) = evaluate_hand(&played, HandModifiers::default(), &[&wild]);
+        assert_eq!(kind, Straight);
+        assert!(same_cards(&scored, &played));
+    }
+
+    /// The ranks of the straight `find_straight` finds in `ranks`.
+    fn straight_in(ranks: &[Rank], modifiers: HandModifiers) -> Vec<Rank> {
+        let suits = [Hearts, Spades, Clubs, Diamonds];
+        let cards: Vec<PlayingCard> = ranks
+            .iter()
+            .zip(suits.iter().cycle())
+            .map(|(&rank, &suit)| card(rank, suit))
+            .collect();
+        let cards: Vec<&PlayingCard> = cards.iter().collect();
+        find_straight(&cards, modifiers)
+            .into_iter()
+            .map(|c| c.rank)
+            .collect()
+    }
+
+    #[test]
+    fn straight_with_ace_low() {
+        let ranks = [Ace, Two, Three, Four, Five];
+        assert_eq!(straight_in(&ranks, HandModifiers::default()), ranks);
+    }
+
+    #[test]
+    fn straight_with_ace_high() {
+        let ranks = [Ten, Jack, Queen, King, Ace];
+        assert_eq!(straight_in(&ranks, HandModifiers::default()), ranks);
+    }
+
+    #[test]
+    fn straight_does_not_wrap() {
+        let ranks = [Queen, King, Ace, Two, Three];
+        assert!(straight_in(&ranks, HandModifiers::default()).is_empty());
+        assert!(straight_in(&ranks, SHORTCUT).is_empty());
+    }
+
+    #[test]
+    fn four_finger_straight_does_not_wrap_past_ace() {
+        let ranks = [King, Ace, Two, Three, Four];
+        assert!(straight_in(&ranks, HandModifiers::default()).is_empty());
+        assert_eq!(straight_in(&ranks, FOUR_FINGERS), [Ace, Two, Three, Four]);
+    }
+
+    #[test]
+    fn shortcut_skips_one_rank_at_a_time() {
+        let ranks = [Two, Three, Six, Seven, Eight];
+        assert!(straight_in(&ranks, SHORTCUT).is_empty());
+        let ranks = [Two, Three, Five, Six, Eight];
+        assert_eq!(straight_in(&ranks, SHORTCUT), ranks);
+    }
+
+    #[test]
+    fn shortcut_with_four_fingers_reaches_ace_high() {
+        let ranks = [Nine, Jack, King, Ace, Four];
+        let both = HandModifiers {
+            shortcut: true,
+            ..FOUR_FINGERS
+        };
+        assert!(straight_in(&ranks, SHORTCUT).is_empty());
+        assert!(straight_in(&ranks, FOUR_FINGERS).is_empty());
+        assert_eq!(straight_in(&ranks, both), [Nine, Jack, King, Ace]);
+    }
+
+    #[test]
+    fn four_card_straight_flush_at_the_ace() {
+        let ace = card(Ace, Hearts);
+        let two = card(Two, Hearts);
+        let three = card(Three, Hearts);
+        let nine = card(Nine, Hearts);
+        let four = card(Four, Spades);
+        let played = [&ace, &two, &three, &nine, &four];
+        let (kind, scored) = evaluate_hand(&played, FOUR_FINGERS, &[]);
+        assert_eq!(kind, StraightFlush);
+        assert!(same_cards(&scored, &played));
+    }
+
+    const RANKS: [Rank; 13] = [
+        Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace,
+    ];
+
+    /// Every way of picking `len` ranks from `RANKS[from..]`, repeats allowed.
+    fn rank_combinations(len: usize, from: usize) -> Vec<Vec<Rank>> {
+        if len == 0 {
+            return vec![Vec::new()];
+        }
+        (from..RANKS.len())
+            .flat_map(|i| {
+                rank_combinations(len - 1, i)
+                    .into_iter()
+                    .map(move |mut rest| {
+                        rest.insert(0, RANKS[i]);
+                        rest
+                    })
+            })
+            .collect()
+    }
+
+    /// Straight detection by brute force, to check `find_straight` against:
+    /// the ranks of every run of positions long enough for a straight, with
+    /// Ace at both position 0 and position 13.
+    fn reference_straight(ranks: &[Rank], modifiers: HandModifiers) -> Vec<Rank> {
+        let mut positions: Vec<(usize, Rank)> = Vec::new();
+        if ranks.contains(&Ace) {
+            positions.push((0, Ace));
+        }
+        for (i, &rank) in RANKS.iter().enumerate() {
+            if ranks.contains(&rank) {
+                positions.push((i + 1, rank));
+            }
+        }
+        let max_gap = if modifiers.shortcut { 2 } else { 1 };
+        let mut found = Vec::new();
+        for mask in 0u32..1 << positions.len() {
+            let run: Vec<(usize, Rank)> = positions
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| (mask >> i) & 1 == 1)
+                .map(|(_, &p)| p)
+                .collect();
+            if run.len() >= modifiers.run_length()
+                && run.windows(2).all(|w| w[1].0 - w[0].0 <= max_gap)
+            {
+                found.extend(run.iter().map(|&(_, rank)| rank));
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    #[test]
+    fn straight_matches_brute_force_for_every_five_ranks() {
+        let both = HandModifiers {
+            shortcut: true,
+            ..FOUR_FINGERS
+        };
+        for ranks in rank_combinations(5, 0) {
+            for modifiers in [HandModifiers::default(), SHORTCUT, FOUR_FINGERS, both] {
+                let mut found = straight_in(&ranks, modifiers);
+                found.sort_unstable();
+                found.dedup();
+                assert_eq!(
+                    found,
+                    reference_straight(&ranks, modifiers),
+                    "{ranks:?} with {modifiers:?}"
+                );
+            }
+        }
+    }
+
+    fn joker(kind: JokerKind) -> jokers::Joker {
+        jokers::Joker {
+            kind,
+            edition: None,
+        }
+    }
+
+    /// The ability each of `jokers` uses.
+    fn resolve(jokers: &[jokers::Joker]) -> Vec<Option<&JokerKind>> {
+        resolve_jokers(&jokers.iter().collect::<Vec<_>>())
+    }
+
+    /// The score of `state`, whose jokers must all be supported. The scores
+    /// here are whole, so this rounds off the error of the base ten mantissa.
+    fn total(state: &GameState) -> f64 {
+        score(state).unwrap().expected().to_f64().round()
+    }
+
+    /// A run at the start of the game, playing `selected` while holding `held`.
+    fn state<'a>(
+        selected: &[&'a PlayingCard],
+        held: &[&'a PlayingCard],
+        jokers: &'a [jokers::Joker],
+    ) -> GameState<'a> {
+        GameState {
+            selected: selected.to_vec(),
+            held: held.to_vec(),
+            debuffed: Vec::new(),
+            jokers: jokers.iter().collect(),
+            hand_levels: Rc::default(),
+            money: 4,
+            hands: 4,
+            discards: 3,
+            rules: DeckRules::for_deck(Deck::Red),
+            boss: None,
         }
-        None => false,
+    }
+
+    #[test]
+    fn pair_scores_its_level_and_both_cards() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let two = card(Two, Spades);
+        let total = total(&state(&[&kings[0], &kings[1], &two], &[], &[]));
+        assert_eq!(total, (10.0 + 10.0 + 10.0) * 2.0);
+    }
+
+    #[test]
+    fn nothing_selected_scores_nothing() {
+        let king = card(King, Hearts);
+        assert_eq!(total(&state(&[], &[&king], &[])), 0.0);
+    }
+
+    #[test]
+    fn joker_adds_mult() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let jokers = [joker(Joker)];
+        let total = total(&state(&[&kings[0], &kings[1]], &[], &jokers));
+        assert_eq!(total, 30.0 * (2.0 + 4.0));
+    }
+
+    #[test]
+    fn held_steel_multiplies_mult() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let steel = enhanced(Queen, Spades, Steel);
+        let total = total(&state(&[&kings[0], &kings[1]], &[&steel], &[]));
+        assert_eq!(total, 30.0 * 2.0 * 1.5);
+    }
+
+    #[test]
+    fn red_seal_retriggers_held_steel() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let steel = PlayingCard {
+            seal: Some(Red),
+            ..enhanced(Queen, Spades, Steel)
+        };
+        let total = total(&state(&[&kings[0], &kings[1]], &[&steel], &[]));
+        assert_eq!(total, 30.0 * 2.0 * 1.5 * 1.5);
+    }
+
+    #[test]
+    fn hanging_chad_and_red_seal_retrigger_the_first_card() {
+        let five = PlayingCard {
+            seal: Some(Red),
+            ..card(Five, Hearts)
+        };
+        let other_five = card(Five, Clubs);
+        let jokers = [joker(HangingChad)];
+        let total = total(&state(&[&five, &other_five], &[], &jokers));
+        // The first Five triggers once, again for its seal and twice more for
+        // Hanging Chad
+        assert_eq!(total, (10.0 + 4.0 * 5.0 + 5.0) * 2.0);
+    }
+
+    #[test]
+    fn dusk_retriggers_only_on_the_final_hand() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let jokers = [joker(Dusk)];
+        let mut state = state(&[&kings[0], &kings[1]], &[], &jokers);
+        assert_eq!(total(&state), 30.0 * 2.0);
+        state.hands = 1;
+        assert_eq!(total(&state), (10.0 + 2.0 * 20.0) * 2.0);
+    }
+
+    #[test]
+    fn mime_retriggers_held_steel() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let steel = enhanced(Queen, Spades, Steel);
+        let jokers = [joker(Mime)];
+        let total = total(&state(&[&kings[0], &kings[1]], &[&steel], &jokers));
+        assert_eq!(total, 30.0 * 2.0 * 1.5 * 1.5);
+    }
+
+    #[test]
+    fn raised_fist_doubles_the_lowest_held_rank() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let held = [card(Seven, Hearts), card(Three, Spades)];
+        let jokers = [joker(RaisedFist)];
+        let total = total(&state(
+            &[&kings[0], &kings[1]],
+            &[&held[0], &held[1]],
+            &jokers,
+        ));
+        assert_eq!(total, 30.0 * (2.0 + 2.0 * 3.0));
+    }
+
+    #[test]
+    fn raised_fist_triggers_once_for_tied_ranks() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let threes = [card(Three, Hearts), card(Three, Spades)];
+        let jokers = [joker(RaisedFist)];
+        let total = total(&state(
+            &[&kings[0], &kings[1]],
+            &[&threes[0], &threes[1]],
+            &jokers,
+        ));
+        assert_eq!(total, 30.0 * (2.0 + 2.0 * 3.0));
+    }
+
+    #[test]
+    fn raised_fist_ignores_stone_cards() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let stone = enhanced(Two, Hearts, Stone);
+        let five = card(Five, Spades);
+        let jokers = [joker(RaisedFist)];
+        let total = total(&state(&[&kings[0], &kings[1]], &[&stone, &five], &jokers));
+        assert_eq!(total, 30.0 * (2.0 + 2.0 * 5.0));
+    }
+
+    #[test]
+    fn raised_fist_does_nothing_when_the_lowest_is_debuffed() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let three = card(Three, Spades);
+        let seven = card(Seven, Hearts);
+        let jokers = [joker(RaisedFist)];
+        let mut state = state(&[&kings[0], &kings[1]], &[&three, &seven], &jokers);
+        state.debuffed = vec![&three];
+        assert_eq!(total(&state), 30.0 * 2.0);
+    }
+
+    #[test]
+    fn midas_mask_turns_face_cards_gold_before_they_score() {
+        let mult_king = enhanced(King, Hearts, Mult);
+        let king = card(King, Clubs);
+        assert_eq!(
+            total(&state(&[&mult_king, &king], &[], &[])),
+            30.0 * (2.0 + 4.0)
+        );
+        let jokers = [joker(MidasMask)];
+        assert_eq!(
+            total(&state(&[&mult_king, &king], &[], &jokers)),
+            30.0 * 2.0
+        );
+    }
+
+    #[test]
+    fn glass_shatters_unless_its_enhancement_is_gone() {
+        let glass_king = enhanced(King, Hearts, Glass);
+        let king = card(King, Clubs);
+        let shatter_chance = |owned: &[jokers::Joker]| -> f64 {
+            score(&state(&[&glass_king, &king], &[], owned))
+                .unwrap()
+                .outcomes
+                .iter()
+                .filter(|(_, s)| s.shattered != 0)
+                .map(|(p, _)| p)
+                .sum()
+        };
+        assert_eq!(shatter_chance(&[]), 0.25);
+        assert_eq!(shatter_chance(&[joker(Vampire { xmult: 1.0 })]), 0.0);
+        assert_eq!(shatter_chance(&[joker(MidasMask)]), 0.0);
+    }
+
+    #[test]
+    fn blueprint_chain_copies_the_joker_at_its_end() {
+        let jokers = [joker(Blueprint), joker(Blueprint), joker(Joker)];
+        assert_eq!(resolve(&jokers), [Some(&Joker), Some(&Joker), Some(&Joker)]);
+    }
+
+    #[test]
+    fn brainstorm_in_the_first_slot_copies_nothing() {
+        let jokers = [joker(Brainstorm), joker(Joker)];
+        assert_eq!(resolve(&jokers), [None, Some(&Joker)]);
+    }
+
+    #[test]
+    fn blueprint_in_the_last_slot_copies_nothing() {
+        let jokers = [joker(Joker), joker(Blueprint)];
+        assert_eq!(resolve(&jokers), [Some(&Joker), None]);
+    }
+
+    #[test]
+    fn blueprint_cannot_copy_four_fingers() {
+        let jokers = [joker(Blueprint), joker(FourFingers)];
+        assert_eq!(resolve(&jokers), [None, Some(&FourFingers)]);
+    }
+
+    #[test]
+    fn the_flint_halves_the_base_rounding_up() {
+        let king = card(King, Hearts);
+        let jokers = [joker(Joker)];
+        let mut state = state(&[&king], &[], &jokers);
+        state.boss = Some(BossBlind::TheFlint);
+        // 5 chips and 1 mult become 3 and 1, not 2 and 0
+        assert_eq!(total(&state), (3.0 + 10.0) * (1.0 + 4.0));
+    }
+
+    #[test]
+    fn the_psychic_scores_nothing_under_five_cards() {
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let others = [card(Two, Spades), card(Five, Diamonds), card(Nine, Clubs)];
+        let mut pair = state(&[&kings[0], &kings[1]], &[], &[]);
+        pair.boss = Some(BossBlind::ThePsychic);
+        assert_eq!(total(&pair), 0.0);
+        let selected = [&kings[0], &kings[1], &others[0], &others[1], &others[2]];
+        let mut five = state(&selected, &[], &[]);
+        five.boss = Some(BossBlind::ThePsychic);
+        assert_eq!(total(&five), 30.0 * 2.0);
+    }
+
+    #[test]
+    fn the_club_debuffs_wild_cards() {
+        let wild = enhanced(King, Spades, Wild);
+        let king = card(King, Hearts);
+        assert!(boss_debuffs(BossBlind::TheClub, &wild, false, false));
+        assert!(!boss_debuffs(BossBlind::TheClub, &king, false, false));
+        let mut state = state(&[&wild, &king], &[], &[]);
+        state.boss = Some(BossBlind::TheClub);
+        state.debuffed = vec![&wild];
+        assert_eq!(total(&state), (10.0 + 10.0) * 2.0);
+    }
+
+    #[test]
+    fn holographic_card_adds_mult() {
+        let holographic_king = PlayingCard {
+            edition: Some(CardEdition::Holographic),
+            ..card(King, Hearts)
+        };
+        let king = card(King, Clubs);
+        let total = total(&state(&[&holographic_king, &king], &[], &[]));
+        assert_eq!(total, 30.0 * (2.0 + 10.0));
+    }
+
+    #[test]
+    fn gold_seal_pays_when_scored() {
+        let gold_king = PlayingCard {
+            seal: Some(Seal::Gold),
+            ..card(King, Hearts)
+        };
+        let king = card(King, Clubs);
+        let score = score(&state(&[&gold_king, &king], &[], &[])).unwrap();
+        assert_eq!(score.expected_money(), 3.0);
+    }
+
+    #[test]
+    fn glass_multiplies_before_the_edition_adds() {
+        let glass_king = PlayingCard {
+            edition: Some(CardEdition::Holographic),
+            ..enhanced(King, Hearts, Glass)
+        };
+        let king = card(King, Clubs);
+        let total = total(&state(&[&glass_king, &king], &[], &[]));
+        assert_eq!(total, 30.0 * (2.0 * 2.0 + 10.0));
+    }
+
+    #[test]
+    fn vampired_stone_card_scores_its_rank() {
+        let stone = enhanced(King, Spades, Stone);
+        let kings = [card(King, Hearts), card(King, Clubs)];
+        let jokers = [joker(Vampire { xmult: 1.0 })];
+        let total = total(&state(&[&stone, &kings[0], &kings[1]], &[], &jokers));
+        assert_eq!(total, (10.0 + 3.0 * 10.0) * (2.0 * 1.1));
     }
 }
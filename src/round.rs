@@ -0,0 +1,154 @@
+use std::cmp::Ordering;
+
+use remotro::balatro::{
+    deck::Rank::{Jack, King, Queen},
+    jokers::JokerKind::{self, *},
+    play::Play,
+};
+
+use crate::{
+    big_num::BigNum,
+    discard,
+    draw_pile::DrawPile,
+    play::{GameState, ScoreDistribution, score},
+    search::{self, chips_needed},
+};
+
+/// What to do with the hand in front of us.
+#[derive(Clone, Debug)]
+pub enum Turn {
+    Play(Vec<usize>, ScoreDistribution),
+    Discard(Vec<usize>),
+}
+
+/// Decides the next move of the round. Beats the blind straight away when
+/// possible, and otherwise aims for each hand left to make its share of the
+/// chips, digging for better cards with discards or throwaway hands when
+/// that gives a better chance. `None` if there's nothing to play.
+pub fn plan_turn(play: &Play, pile: &DrawPile) -> Option<Turn> {
+    // Every hand scored this turn starts from the same snapshot
+    let base = GameState::from_play(play, &[]);
+    let (cards, scores) = search::best_hand(play, &base).unwrap_or_else(|e| {
+        println!("{e}, ignoring jokers");
+        search::best_hand_without_jokers(play, &base)
+    })?;
+    let needed = chips_needed(play);
+    let finish_chance = scores.chance_to_reach(needed);
+    if finish_chance >= 1.0 {
+        return Some(Turn::Play(cards, scores));
+    }
+    let hands = u32::from(play.hands());
+    let discards = u32::from(play.discards());
+    let owns = |wanted: fn(&JokerKind) -> bool| play.jokers().iter().any(|j| wanted(&j.kind));
+    let target = share(needed, hands);
+    let (cards, scores) = if owns(|k| matches!(k, RideTheBus { .. })) {
+        avoid_faces(
+            play,
+            &base,
+            (cards, scores),
+            target,
+            owns(|k| *k == Pareidolia),
+        )
+    } else {
+        (cards, scores)
+    };
+    if hands > 1
+        && owns(|k| matches!(k, Dusk | Acrobat))
+        && let Some(throwaway) = save_for_last_hand(play, &base, &cards, needed, finish_chance)
+    {
+        return Some(throwaway);
+    }
+    // Every hand played melts Ice Cream, so don't spend hands digging with it.
+    // Popcorn only shrinks between rounds, so it doesn't change anything here
+    let can_dig = hands > 1 && !owns(|k| matches!(k, IceCream { .. }));
+    if discards == 0 && !can_dig {
+        return Some(Turn::Play(cards, scores));
+    }
+    // Digging with a hand leaves one fewer to make up the chips
+    let dig_target = if discards > 0 {
+        target
+    } else {
+        share(needed, hands - 1)
+    };
+    let now = (scores.chance_to_reach(target), scores.expected());
+    if let Ok(Some((thrown, value))) = discard::best_draw(play, &base, pile, dig_target) {
+        // Green Joker loses mult for every discard, so only discard for a
+        // better chance of beating the blind
+        let better = if discards > 0 && owns(|k| matches!(k, GreenJoker { .. })) {
+            value.0 > now.0
+        } else {
+            value > now
+        };
+        if better && discards > 0 {
+            return Some(Turn::Discard(thrown));
+        }
+        if better && let Ok(thrown_scores) = score(&base.select(play, &thrown)) {
+            return Some(Turn::Play(thrown, thrown_scores));
+        }
+    }
+    Some(Turn::Play(cards, scores))
+}
+
+/// The score each of `hands` hands needs to make for `needed` to be reached.
+fn share(needed: BigNum, hands: u32) -> BigNum {
+    needed * (1.0 / f64::from(hands.max(1)))
+}
+
+/// Ride the Bus resets whenever a face card scores, so when the blind can't
+/// be beaten this hand anyway, play without face cards if that does as well.
+fn avoid_faces<'a>(
+    play: &'a Play,
+    base: &GameState<'a>,
+    best: (Vec<usize>, ScoreDistribution),
+    target: BigNum,
+    pareidolia: bool,
+) -> (Vec<usize>, ScoreDistribution) {
+    let faces: Vec<usize> = play
+        .hand()
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| {
+            c.card
+                .as_ref()
+                .is_some_and(|c| pareidolia || matches!(c.rank, Jack | Queen | King))
+        })
+        .map(|(i, _)| i)
+        .collect();
+    if !best.0.iter().any(|i| faces.contains(i)) {
+        return best;
+    }
+    match search::best_hand_excluding(play, base, &faces) {
+        Ok(Some(other)) if other.1.chance_to_reach(target) >= best.1.chance_to_reach(target) => {
+            other
+        }
+        _ => best,
+    }
+}
+
+/// Dusk and Acrobat only trigger on the last hand, so if `cards` have a better
+/// chance of beating the blind then, play the card worth least in the
+/// meantime and hold on to them.
+fn save_for_last_hand<'a>(
+    play: &'a Play,
+    base: &GameState<'a>,
+    cards: &[usize],
+    needed: BigNum,
+    finish_chance: f64,
+) -> Option<Turn> {
+    let mut last = base.select(play, cards);
+    last.hands = 1;
+    if score(&last).ok()?.chance_to_reach(needed) <= finish_chance {
+        return None;
+    }
+    play.hand()
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| c.card.is_some() && !cards.contains(i))
+        .filter_map(|(i, _)| Some((vec![i], score(&base.select(play, &[i])).ok()?)))
+        .min_by(|a, b| {
+            a.1.expected()
+                .partial_cmp(&b.1.expected())
+                .unwrap_or(Ordering::Equal)
+        })
+        .map(|(card, scores)| Turn::Play(card, scores))
+}
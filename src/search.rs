@@ -0,0 +1,233 @@
+use remotro::balatro::{
+    jokers::{
+        Joker, JokerEdition,
+        JokerKind::{self, *},
+    },
+    play::Play,
+};
+
+use crate::{
+    big_num::BigNum,
+    play::{GameState, ScoreDistribution, ScoreError, score},
+};
+
+/// The most cards Balatro lets you play in a single hand.
+const MAX_PLAYED: usize = 5;
+/// Past this many jokers whose order matters, trying every order of them takes
+/// too long.
+const MAX_PERMUTED_JOKERS: usize = 6;
+
+/// Tries every 1-5 card subset of the hand and returns the indices of the
+/// one most likely to beat the blind along with its scores. Ties go to the
+/// higher expected score, then to whichever earns more money.
+/// `base` is `play` snapshotted with nothing selected.
+pub fn best_hand<'a>(
+    play: &'a Play,
+    base: &GameState<'a>,
+) -> Result<Option<(Vec<usize>, ScoreDistribution)>, ScoreError> {
+    search(play, base, true, &[])
+}
+
+/// Like [`best_hand`], but never plays the cards at `excluded`.
+pub fn best_hand_excluding<'a>(
+    play: &'a Play,
+    base: &GameState<'a>,
+    excluded: &[usize],
+) -> Result<Option<(Vec<usize>, ScoreDistribution)>, ScoreError> {
+    search(play, base, true, excluded)
+}
+
+/// Like [`best_hand`], but scores as if no jokers were owned. A fallback for
+/// when the jokers can't be scored.
+pub fn best_hand_without_jokers<'a>(
+    play: &'a Play,
+    base: &GameState<'a>,
+) -> Option<(Vec<usize>, ScoreDistribution)> {
+    search(play, base, false, &[]).ok().flatten()
+}
+
+fn search<'a>(
+    play: &'a Play,
+    base: &GameState<'a>,
+    with_jokers: bool,
+    excluded: &[usize],
+) -> Result<Option<(Vec<usize>, ScoreDistribution)>, ScoreError> {
+    let target = chips_needed(play);
+    let playable: Vec<usize> = play
+        .hand()
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| c.card.is_some() && !excluded.contains(i))
+        .map(|(i, _)| i)
+        .collect();
+    let mut best: Option<(Vec<usize>, ScoreDistribution)> = None;
+    for size in 1..=MAX_PLAYED.min(playable.len()) {
+        for subset in combinations(&playable, size) {
+            let mut state = base.select(play, &subset);
+            if !with_jokers {
+                state.jokers.clear();
+            }
+            let value = score(&state)?;
+            if best
+                .as_ref()
+                .is_none_or(|(_, b)| rank(&value, target) > rank(b, target))
+            {
+                best = Some((subset, value));
+            }
+        }
+    }
+    Ok(best)
+}
+
+/// The order to put the jokers in, as indices into `play.jokers()`, that
+/// scores playing `cards` best. `None` if the current order is already best.
+///
+/// Jokers that only add chips or mult score the same wherever they are, as
+/// long as they come before the ones that multiply, so only the multiplying
+/// jokers are rearranged behind them. Blueprint and Brainstorm copy by
+/// position, so owning either means trying every order.
+pub fn best_joker_order(play: &Play, cards: &[usize]) -> Result<Option<Vec<usize>>, ScoreError> {
+    let current = GameState::from_play(play, cards);
+    let unchanged: Vec<usize> = (0..current.jokers.len()).collect();
+    let copies = current
+        .jokers
+        .iter()
+        .any(|j| matches!(j.kind, Blueprint | Brainstorm));
+    let (ordered, additive): (Vec<usize>, Vec<usize>) = unchanged
+        .iter()
+        .partition(|&&i| copies || is_order_sensitive(current.jokers[i]));
+    if ordered.len() > MAX_PERMUTED_JOKERS {
+        println!(
+            "Not reordering jokers, {} of them depend on their order",
+            ordered.len()
+        );
+        return Ok(None);
+    }
+    let target = chips_needed(play);
+    let mut best = (rank(&score(&current)?, target), unchanged.clone());
+    for permutation in permutations(&ordered) {
+        let order: Vec<usize> = additive.iter().copied().chain(permutation).collect();
+        let mut state = current.clone();
+        state.jokers = order.iter().map(|&i| current.jokers[i]).collect();
+        let value = rank(&score(&state)?, target);
+        if value > best.0 {
+            best = (value, order);
+        }
+    }
+    Ok((best.1 != unchanged).then_some(best.1))
+}
+
+/// Whether where `joker` sits changes the score, because it multiplies mult
+/// that jokers before it added to.
+fn is_order_sensitive(joker: &Joker) -> bool {
+    joker.edition == Some(JokerEdition::Polychrome)
+        || matches!(
+            joker.kind,
+            Stencil { .. }
+                | LoyaltyCard { .. }
+                | SteelJoker { .. }
+                | Cavendish { .. }
+                | Blackboard
+                | CardSharp
+                | Madness { .. }
+                | Vampire { .. }
+                | Hologram { .. }
+                | Baron
+                | Obelisk { .. }
+                | Photograph
+                | Ancient { .. }
+                | Ramen { .. }
+                | Campfire { .. }
+                | Acrobat
+                | Throwback { .. }
+                | Bloodstone { .. }
+                | JokerKind::Glass { .. }
+                | FlowerPot
+                | Idol { .. }
+                | SeeingDouble
+                | HitTheRoad { .. }
+                | Duo
+                | Trio
+                | Family
+                | Order
+                | Tribe
+                | DriversLicense { .. }
+                | Caino { .. }
+                | Triboulet
+                | Yorick { .. }
+                | Constellation { .. }
+                // Adds chips for money earned by the jokers before it
+                | Bull
+                // Vampire only eats the Gold it makes when it comes first
+                | MidasMask { .. }
+        )
+}
+
+/// The `(from, to)` joker moves that rearrange the jokers into `order`.
+pub fn joker_moves(order: &[usize]) -> Vec<(usize, usize)> {
+    let mut current: Vec<usize> = (0..order.len()).collect();
+    let mut moves = Vec::new();
+    for (to, joker) in order.iter().enumerate() {
+        let from = current.iter().position(|j| j == joker).unwrap();
+        if from != to {
+            let moved = current.remove(from);
+            current.insert(to, moved);
+            moves.push((from, to));
+        }
+    }
+    moves
+}
+
+/// Chips still needed to beat the current blind.
+pub fn chips_needed(play: &Play) -> BigNum {
+    BigNum::from((play.blind().chips as f64 - play.score() as f64).max(0.0))
+}
+
+fn rank(scores: &ScoreDistribution, target: BigNum) -> (f64, BigNum, f64) {
+    (
+        scores.chance_to_reach(target),
+        scores.expected(),
+        scores.expected_money(),
+    )
+}
+
+/// The cards that need clicking to turn the current in-game selection into
+/// `target`.
+pub fn clicks_for(play: &Play, target: &[usize]) -> Vec<usize> {
+    play.hand()
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| c.selected != target.contains(i))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+pub fn combinations(items: &[usize], size: usize) -> Vec<Vec<usize>> {
+    if size == 0 {
+        return vec![Vec::new()];
+    }
+    let mut out = Vec::new();
+    for (i, &item) in items.iter().enumerate() {
+        for mut rest in combinations(&items[i + 1..], size - 1) {
+            rest.insert(0, item);
+            out.push(rest);
+        }
+    }
+    out
+}
+
+fn permutations(items: &[usize]) -> Vec<Vec<usize>> {
+    if items.is_empty() {
+        return vec![Vec::new()];
+    }
+    let mut out = Vec::new();
+    for (i, &item) in items.iter().enumerate() {
+        let mut rest = items.to_vec();
+        rest.remove(i);
+        for mut permutation in permutations(&rest) {
+            permutation.insert(0, item);
+            out.push(permutation);
+        }
+    }
+    out
+}
@@ -0,0 +1,367 @@
+use remotro::balatro::{
+    consumables::{Consumable, Planet, Spectral, Tarot},
+    deck::{Enhancement, PlayingCard, Seal},
+    jokers::{Joker, JokerEdition},
+    packs::{Pack, PackCard, PackKind, PackSize},
+    play::Play,
+    shop::{MainCard, Shop, Voucher},
+};
+
+use crate::{
+    benchmark::{Benchmark, ROUNDS_AHEAD, is_plain},
+    decks::DeckRules,
+    play::GameState,
+    search,
+};
+
+/// Interest is $1 for every $5 held, up to this much until Seed Money and
+/// Money Tree raise it.
+const MAX_INTEREST: i64 = 5;
+/// How much more consumables are worth on decks that favour them.
+const FAVOURED_CONSUMABLES: f64 = 1.5;
+/// How much Clearance Sale, and Liquidation after it, take off prices.
+const DISCOUNT: f64 = 0.25;
+const ALL_PLANETS: [Planet; 12] = [
+    Planet::Pluto,
+    Planet::Mercury,
+    Planet::Uranus,
+    Planet::Venus,
+    Planet::Saturn,
+    Planet::Jupiter,
+    Planet::Earth,
+    Planet::Mars,
+    Planet::Neptune,
+    Planet::PlanetX,
+    Planet::Ceres,
+    Planet::Eris,
+];
+
+/// Something to buy from the shop.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Purchase {
+    Card(usize),
+    Voucher(usize),
+    Pack(usize),
+}
+
+/// The offer most worth buying, once the interest its price would have
+/// earned is taken into account. `None` once nothing is worth it, which is
+/// when to leave.
+pub fn next_purchase(shop: &Shop, benchmark: &Benchmark) -> Option<Purchase> {
+    let money: i64 = shop.money().into();
+    let owned = shop.jokers();
+    let rules = benchmark
+        .rules()
+        .unwrap_or_else(|| DeckRules::for_deck(shop.run_info().deck));
+    let free_slots = free_joker_slots(owned, rules);
+    // Planets and tarots have to fit in a consumable slot before they're used
+    let consumable_slot = shop.consumables().len() < rules.consumable_slots;
+    let favour = if rules.favours_consumables {
+        FAVOURED_CONSUMABLES
+    } else {
+        1.0
+    };
+    let cap = interest_cap(&shop.run_info().vouchers);
+    let mut cards = Vec::new();
+    let mut jokers = Vec::new();
+    let mut unfit_joker: f64 = 0.0;
+    for offer in shop.main_cards() {
+        let worth = if let MainCard::Joker(joker) = &offer.card {
+            let worth = benchmark.joker_worth(owned, joker);
+            if fits(joker, free_slots) {
+                jokers.push(worth);
+                worth
+            } else {
+                unfit_joker = unfit_joker.max(worth);
+                0.0
+            }
+        } else if let Some(consumable) = as_consumable(&offer.card)
+            && consumable_slot
+        {
+            let left = money - i64::from(offer.price);
+            consumable_worth(&consumable, benchmark, owned, left) * favour
+        } else {
+            0.0
+        };
+        cards.push((worth, offer.price));
+    }
+    let mut offers: Vec<_> = cards
+        .iter()
+        .enumerate()
+        .map(|(i, &(worth, price))| (worth, price, Purchase::Card(i)))
+        .collect();
+    for (i, offer) in shop.vouchers().iter().enumerate() {
+        let worth = match offer.voucher {
+            // A slot for the best joker there's no room for
+            Voucher::Antimatter => unfit_joker,
+            voucher => voucher_worth(voucher, benchmark, owned, money, cap, &cards),
+        };
+        offers.push((worth, offer.price, Purchase::Voucher(i)));
+    }
+    for (i, offer) in shop.boosters().iter().enumerate() {
+        let pool: Vec<f64> = match offer.pack.kind {
+            PackKind::Celestial => ALL_PLANETS
+                .iter()
+                .map(|&planet| benchmark.planet_worth(owned, planet) * favour)
+                .collect(),
+            // The jokers on offer stand in for what the pack might hold
+            PackKind::Buffoon if free_slots > 0 => jokers.clone(),
+            // Tarots and spectrals from packs have to be used on the spot,
+            // and adding playing cards to the deck isn't modelled
+            _ => Vec::new(),
+        };
+        let worth = expected_best(pool, pack_shown(offer.pack.kind, offer.pack.size));
+        offers.push((worth, offer.price, Purchase::Pack(i)));
+    }
+    offers
+        .into_iter()
+        .filter(|&(_, price, _)| i64::from(price) <= money)
+        .map(|(worth, price, purchase)| {
+            let lost_interest =
+                (interest(money, cap) - interest(money - i64::from(price), cap)) as f64;
+            (
+                worth - f64::from(price) - lost_interest * ROUNDS_AHEAD,
+                purchase,
+            )
+        })
+        .filter(|(gain, _)| *gain > 0.0)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, purchase)| purchase)
+}
+
+/// The index of a held consumable to use straight away, along with the planet
+/// it is if it's one. Planets, Black Hole and the Hermit are always worth
+/// using as soon as they're bought.
+pub fn consumable_to_use(shop: &Shop) -> Option<(usize, Option<Planet>)> {
+    shop.consumables()
+        .iter()
+        .enumerate()
+        .find_map(|(i, c)| match c {
+            Consumable::Planet(planet) => Some((i, Some(*planet))),
+            Consumable::Tarot(Tarot::Hermit) | Consumable::Spectral(Spectral::BlackHole) => {
+                Some((i, None))
+            }
+            _ => None,
+        })
+}
+
+/// The index of a held consumable that changes cards, along with the hand
+/// cards to use it on. It's aimed at the plain cards of the best hand, since
+/// they're about to score.
+pub fn card_change_to_use(play: &Play) -> Option<(usize, Vec<usize>)> {
+    let (i, count) = play
+        .consumables()
+        .iter()
+        .enumerate()
+        .find_map(|(i, c)| Some((i, card_change(c)?.0)))?;
+    let base = GameState::from_play(play, &[]);
+    let (best, _) = search::best_hand(play, &base).ok().flatten()?;
+    let targets: Vec<usize> = best
+        .into_iter()
+        .filter(|&j| play.hand()[j].card.as_ref().is_some_and(is_plain))
+        .take(count)
+        .collect();
+    (!targets.is_empty()).then_some((i, targets))
+}
+
+/// The card to take from an open pack, or `None` to skip it.
+pub fn pack_choice(pack: &Pack, benchmark: &Benchmark) -> Option<usize> {
+    let owned = pack.jokers();
+    // Nothing has been played yet to value jokers by without the rules
+    let free_slots = benchmark
+        .rules()
+        .map_or(0, |rules| free_joker_slots(owned, rules));
+    pack.cards()
+        .iter()
+        .enumerate()
+        .map(|(i, card)| {
+            let worth = match card {
+                PackCard::Joker(joker) if fits(joker, free_slots) => {
+                    benchmark.joker_worth(owned, joker)
+                }
+                PackCard::Planet(planet) => benchmark.planet_worth(owned, *planet),
+                _ => 0.0,
+            };
+            (worth, i)
+        })
+        .filter(|(worth, _)| *worth > 0.0)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, i)| i)
+}
+
+/// Joker slots left over by the `owned` jokers. Negative jokers don't take
+/// one up.
+fn free_joker_slots(owned: &[Joker], rules: DeckRules) -> usize {
+    rules.joker_slots.saturating_sub(
+        owned
+            .iter()
+            .filter(|j| j.edition != Some(JokerEdition::Negative))
+            .count(),
+    )
+}
+
+/// Whether there's room for `joker` with `free_slots` joker slots left.
+fn fits(joker: &Joker, free_slots: usize) -> bool {
+    free_slots > 0 || joker.edition == Some(JokerEdition::Negative)
+}
+
+/// The most interest paid a round with the `owned` vouchers.
+fn interest_cap(owned: &[Voucher]) -> i64 {
+    if owned.contains(&Voucher::MoneyTree) {
+        20
+    } else if owned.contains(&Voucher::SeedMoney) {
+        10
+    } else {
+        MAX_INTEREST
+    }
+}
+
+fn interest(money: i64, cap: i64) -> i64 {
+    (money / 5).clamp(0, cap)
+}
+
+fn as_consumable(card: &MainCard) -> Option<Consumable> {
+    match card {
+        MainCard::Planet(planet) => Some(Consumable::Planet(*planet)),
+        MainCard::Tarot(tarot) => Some(Consumable::Tarot(*tarot)),
+        MainCard::Spectral(spectral) => Some(Consumable::Spectral(*spectral)),
+        _ => None,
+    }
+}
+
+/// What using `consumable` is worth in dollars, with `money` held after
+/// buying it.
+fn consumable_worth(
+    consumable: &Consumable,
+    benchmark: &Benchmark,
+    owned: &[Joker],
+    money: i64,
+) -> f64 {
+    match consumable {
+        Consumable::Planet(planet) => benchmark.planet_worth(owned, *planet),
+        // Doubles money, up to $20
+        Consumable::Tarot(Tarot::Hermit) => money.clamp(0, 20) as f64,
+        Consumable::Spectral(Spectral::BlackHole) => benchmark.black_hole_worth(owned),
+        consumable => card_change(consumable).map_or(0.0, |(cards, change)| {
+            benchmark.card_change_worth(owned, cards, change)
+        }),
+    }
+}
+
+/// How many cards a tarot or spectral changes, and what it does to each.
+/// Ones that change a card's rank or suit are left out, since the draw pile
+/// tells cards apart by those, as are ones that make other cards or jokers or
+/// change random ones.
+fn card_change(consumable: &Consumable) -> Option<(usize, fn(&mut PlayingCard))> {
+    let change: (usize, fn(&mut PlayingCard)) = match consumable {
+        Consumable::Tarot(Tarot::Magician) => (2, |c| c.enhancement = Some(Enhancement::Lucky)),
+        Consumable::Tarot(Tarot::Empress) => (2, |c| c.enhancement = Some(Enhancement::Mult)),
+        Consumable::Tarot(Tarot::Hierophant) => (2, |c| c.enhancement = Some(Enhancement::Bonus)),
+        Consumable::Tarot(Tarot::Lovers) => (1, |c| c.enhancement = Some(Enhancement::Wild)),
+        Consumable::Tarot(Tarot::Chariot) => (1, |c| c.enhancement = Some(Enhancement::Steel)),
+        Consumable::Tarot(Tarot::Justice) => (1, |c| c.enhancement = Some(Enhancement::Glass)),
+        Consumable::Tarot(Tarot::Devil) => (1, |c| c.enhancement = Some(Enhancement::Gold)),
+        Consumable::Tarot(Tarot::Tower) => (1, |c| c.enhancement = Some(Enhancement::Stone)),
+        Consumable::Spectral(Spectral::Talisman) => (1, |c| c.seal = Some(Seal::Gold)),
+        Consumable::Spectral(Spectral::DejaVu) => (1, |c| c.seal = Some(Seal::Red)),
+        _ => return None,
+    };
+    Some(change)
+}
+
+/// What a voucher other than Antimatter is worth in dollars over the next
+/// few rounds, with `money` held and interest paid up to `cap`. `cards` are
+/// this shop's main cards as `(worth, price)`, taken as typical of the shops
+/// to come.
+fn voucher_worth(
+    voucher: Voucher,
+    benchmark: &Benchmark,
+    owned: &[Joker],
+    money: i64,
+    cap: i64,
+    cards: &[(f64, u32)],
+) -> f64 {
+    let typical = |value: fn(f64, u32) -> f64| {
+        cards
+            .iter()
+            .map(|&(worth, price)| value(worth, price))
+            .sum::<f64>()
+            / cards.len().max(1) as f64
+    };
+    match voucher {
+        Voucher::Grabber | Voucher::NachoTong => benchmark.extra_hand_worth(),
+        Voucher::Wasteful | Voucher::Recyclomancy => benchmark.extra_discard_worth(owned),
+        // Raise the most interest paid to $10, then $20
+        Voucher::SeedMoney => (interest(money, 10) - interest(money, cap)) as f64 * ROUNDS_AHEAD,
+        Voucher::MoneyTree => (interest(money, 20) - interest(money, cap)) as f64 * ROUNDS_AHEAD,
+        // Saves on about one card a shop
+        Voucher::ClearanceSale | Voucher::Liquidation => {
+            typical(|_, price| f64::from(price)) * DISCOUNT * ROUNDS_AHEAD
+        }
+        // One more card to pick from each shop
+        Voucher::Overstock | Voucher::OverstockPlus => {
+            typical(|worth, price| (worth - f64::from(price)).max(0.0)) * ROUNDS_AHEAD
+        }
+        // Hand size, shop odds, consumable slots and the rest aren't modelled
+        _ => 0.0,
+    }
+}
+
+/// How many cards a pack of `kind` and `size` shows.
+fn pack_shown(kind: PackKind, size: PackSize) -> usize {
+    match (kind, size) {
+        (PackKind::Buffoon | PackKind::Spectral, PackSize::Normal) => 2,
+        (PackKind::Buffoon | PackKind::Spectral, _) => 4,
+        (_, PackSize::Normal) => 3,
+        _ => 5,
+    }
+}
+
+/// The expected worth of the best of `shown` cards drawn at random from
+/// `pool`, or nothing when none are worth taking. Only one pick is counted,
+/// even from Mega Packs.
+fn expected_best(mut pool: Vec<f64>, shown: usize) -> f64 {
+    let n = pool.len();
+    if n == 0 || shown == 0 {
+        return 0.0;
+    }
+    let shown = shown.min(n);
+    pool.sort_by(|a, b| b.total_cmp(a));
+    // The `j`th best is the best shown when the rest all come from below it
+    pool.iter()
+        .enumerate()
+        .map(|(j, worth)| worth.max(0.0) * choose(n - j - 1, shown - 1) / choose(n, shown))
+        .sum()
+}
+
+fn choose(n: usize, k: usize) -> f64 {
+    if k > n {
+        return 0.0;
+    }
+    (0..k).map(|i| (n - i) as f64 / (i + 1) as f64).product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_best_weighs_each_card_by_its_chance_of_being_the_best_shown() {
+        // Of the pairs from 3, 2 and 1, two have 3 as the best and one has 2
+        assert_eq!(expected_best(vec![1.0, 3.0, 2.0], 2), 8.0 / 3.0);
+        assert_eq!(expected_best(vec![2.0, 4.0], 1), 3.0);
+        assert_eq!(expected_best(vec![1.0, 3.0, 2.0], 5), 3.0);
+        // Nothing is taken rather than a card worth less than nothing
+        assert_eq!(expected_best(vec![-4.0, -2.0], 1), 0.0);
+        assert_eq!(expected_best(Vec::new(), 3), 0.0);
+    }
+
+    #[test]
+    fn seed_money_and_money_tree_raise_the_interest_cap() {
+        assert_eq!(interest(200, interest_cap(&[])), 5);
+        assert_eq!(interest(200, interest_cap(&[Voucher::SeedMoney])), 10);
+        let both = [Voucher::SeedMoney, Voucher::MoneyTree];
+        assert_eq!(interest(200, interest_cap(&both)), 20);
+        assert_eq!(interest(-3, interest_cap(&both)), 0);
+    }
+}
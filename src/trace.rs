@@ -0,0 +1,94 @@
+use std::fmt;
+
+use crate::big_num::BigNum;
+
+/// One effect applied while scoring a hand, with the chips, mult and dollars
+/// earned either side of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceStep {
+    pub source: String,
+    pub chips_before: BigNum,
+    pub mult_before: BigNum,
+    pub money_before: f64,
+    pub chips_after: BigNum,
+    pub mult_after: BigNum,
+    pub money_after: f64,
+}
+
+/// Every step that changed the score of a hand or the money it earns, in the
+/// order the game applies them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .steps
+            .iter()
+            .map(|s| s.source.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        writeln!(
+            f,
+            "{:width$}  {:>25}  {:>25}  {:>15}",
+            "Step", "Chips", "Mult", "Money"
+        )?;
+        for step in &self.steps {
+            writeln!(
+                f,
+                "{:width$}  {:>25}  {:>25}  {:>15}",
+                step.source,
+                format!("{} -> {}", step.chips_before, step.chips_after),
+                format!("{} -> {}", step.mult_before, step.mult_after),
+                format!("{} -> {}", step.money_before, step.money_after),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects a [`Trace`] while scoring, or does nothing when not tracing so
+/// the search doesn't pay for it.
+#[derive(Debug, Default)]
+pub struct Tracer {
+    trace: Option<Trace>,
+}
+
+impl Tracer {
+    pub fn enabled() -> Self {
+        Self {
+            trace: Some(Trace::default()),
+        }
+    }
+
+    /// Records a step if tracing and it changed anything. `before` and
+    /// `after` are the chips, mult and dollars earned so far. `source` is only
+    /// built when it's needed.
+    pub fn record(
+        &mut self,
+        source: impl FnOnce() -> String,
+        before: (BigNum, BigNum, f64),
+        after: (BigNum, BigNum, f64),
+    ) {
+        if let Some(trace) = &mut self.trace
+            && before != after
+        {
+            trace.steps.push(TraceStep {
+                source: source(),
+                chips_before: before.0,
+                mult_before: before.1,
+                money_before: before.2,
+                chips_after: after.0,
+                mult_after: after.1,
+                money_after: after.2,
+            });
+        }
+    }
+
+    pub fn finish(self) -> Trace {
+        self.trace.unwrap_or_default()
+    }
+}
@@ -0,0 +1,100 @@
+use std::{fs::OpenOptions, io::Write};
+
+use remotro::balatro::{CurrentScreen, play::Play};
+
+use crate::{
+    big_num::BigNum,
+    play::{self, GameState, ScoreDistribution},
+    search::chips_needed,
+};
+
+/// Where every hand's prediction gets written, with the details of the wrong
+/// ones so they can become regression tests.
+const PREDICTION_LOG: &str = "score_predictions.log";
+
+/// A hand the bot played, waiting for the game to say what it scored.
+pub struct Prediction {
+    scores: ScoreDistribution,
+    chips_before: f64,
+    hands_before: u32,
+    needed: BigNum,
+    /// The trace and `Play` screen the prediction came from, kept as text since
+    /// the screen is gone once the hand is played.
+    details: String,
+}
+
+impl Prediction {
+    /// Records what playing the selected `cards` is expected to score, just
+    /// before playing them so any joker moves are accounted for. `None` if the
+    /// hand can't be scored, since there's nothing to check it against.
+    pub fn new(play: &Play, cards: &[usize]) -> Option<Self> {
+        let scores = play::score_hand(play).ok()?;
+        let (_, trace) = play::trace(&GameState::from_play(play, cards)).ok()?;
+        Some(Self {
+            scores,
+            chips_before: play.score() as f64,
+            hands_before: play.hands().into(),
+            needed: chips_needed(play),
+            details: format!("Cards: {cards:?}\n{trace}\n{play:#?}"),
+        })
+    }
+
+    /// Compares the prediction against the next screen the game shows.
+    pub fn check(self, next: &CurrentScreen) {
+        match next {
+            // Still the same round, so the round score went up by exactly what
+            // the hand scored
+            CurrentScreen::Play(play) if u32::from(play.hands()) + 1 == self.hands_before => {
+                let actual = play.score() as f64 - self.chips_before;
+                let matched = self
+                    .scores
+                    .outcomes
+                    .iter()
+                    .any(|(_, s)| (s.total().to_f64() - actual).abs() < 0.5);
+                self.report(&format!("{actual}"), matched);
+            }
+            // The hands left didn't go down, so the hand never got played
+            CurrentScreen::Play(_) => println!("The hand wasn't played, not checking it"),
+            // The run was quit, so there's no telling how the hand did
+            CurrentScreen::Menu(_) => {}
+            // The hand was the last one and fell short of the blind
+            CurrentScreen::GameOver(_) => {
+                let matched = self.scores.min() < self.needed;
+                self.report(&format!("less than {}", self.needed), matched);
+            }
+            // The hand ended the round, so all we know is that it beat the blind
+            _ => {
+                let matched = self.scores.max() >= self.needed;
+                self.report(&format!("at least {}", self.needed), matched);
+            }
+        }
+    }
+
+    /// Prints the prediction and logs it, along with everything it was made
+    /// from when it was wrong.
+    fn report(&self, actual: &str, matched: bool) {
+        println!(
+            "Predicted {} ({} to {}), scored {actual}",
+            self.scores.expected(),
+            self.scores.min(),
+            self.scores.max()
+        );
+        let mut entry = format!(
+            "Predicted {} to {}, scored {actual}\n",
+            self.scores.min(),
+            self.scores.max(),
+        );
+        if !matched {
+            println!("Score prediction was wrong, writing it to {PREDICTION_LOG}");
+            entry = format!("MISMATCH {entry}{}\n\n", self.details);
+        }
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(PREDICTION_LOG)
+            .and_then(|mut file| file.write_all(entry.as_bytes()));
+        if let Err(e) = written {
+            println!("Couldn't write to {PREDICTION_LOG}: {e}");
+        }
+    }
+}